@@ -1,6 +1,9 @@
 extern crate log;
 
-use pyembed_downloader::{run, Config, Result};
+use pyembed_downloader::{
+    add, clear_cache, features, remove, resolve, run, size_table, table, upgrade, wheelhouse,
+    Backend, CheckPolicy, Config, InvalidationMode, Result, SourceMode, SourceRule,
+};
 
 struct ConsoleLogger;
 impl log::Log for ConsoleLogger {
@@ -25,6 +28,7 @@ async fn main() -> Result<()> {
         .arg(
             clap::Arg::new("pyver")
                 .long("py-ver")
+                .global(true)
                 .num_args(1)
                 .value_name("ver")
                 .help("下载指定版本的 Python，如 3.8.6"),
@@ -32,30 +36,43 @@ async fn main() -> Result<()> {
         .arg(
             clap::Arg::new("32")
                 .long("32")
+                .global(true)
                 .num_args(0)
                 .help("下载 32 位版本，默认下载 64 位版本"),
         )
         .arg(
             clap::Arg::new("skip-download")
                 .long("skip-download")
+                .global(true)
                 .num_args(0)
                 .help("跳过下载，直接使用已有的文件"),
         )
         .arg(
             clap::Arg::new("dir")
                 .long("dir")
+                .global(true)
                 .num_args(1)
                 .help("工作目录，默认为 <当前目录>\\pyembed_runtime\\"),
         )
+        .arg(
+            clap::Arg::new("output-dir")
+                .long("output-dir")
+                .global(true)
+                .num_args(1)
+                .value_name("dir")
+                .help("输出目录，指定后工作目录保持完整，编译和清理后的运行时导出到此目录"),
+        )
         .arg(
             clap::Arg::new("cachedir")
                 .long("cache-dir")
+                .global(true)
                 .num_args(1)
                 .help("缓存目录，默认为当前目录"),
         )
         .arg(
             clap::Arg::new("python-mirror")
                 .long("python-mirror")
+                .global(true)
                 .num_args(1)
                 .value_name("url")
                 .help("通过指定的镜像站下载 Python 安装包"),
@@ -63,44 +80,429 @@ async fn main() -> Result<()> {
         .arg(
             clap::Arg::new("pip-mirror")
                 .long("pip-mirror")
+                .global(true)
                 .num_args(1)
                 .value_name("url")
                 .help("通过指定 pip 镜像站下载依赖包"),
         )
+        .arg(
+            clap::Arg::new("extra-index-url")
+                .long("extra-index-url")
+                .global(true)
+                .num_args(1)
+                .value_name("url")
+                .action(clap::ArgAction::Append)
+                .help("除 pip 镜像站之外额外使用的索引，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("trusted-host")
+                .long("trusted-host")
+                .global(true)
+                .num_args(1)
+                .value_name("host")
+                .action(clap::ArgAction::Append)
+                .help("信任的主机，允许使用 http 或无效的证书访问，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("pip-arg")
+                .long("pip-arg")
+                .global(true)
+                .num_args(1)
+                .value_name("arg")
+                .allow_hyphen_values(true)
+                .action(clap::ArgAction::Append)
+                .help("原样传给 pip install 的参数，如 --pip-arg=--prefer-binary，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("pip-config")
+                .long("pip-config")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .help("pip 配置文件，通过 PIP_CONFIG_FILE 传给 pip"),
+        )
+        .arg(
+            clap::Arg::new("pip-cache")
+                .long("pip-cache")
+                .global(true)
+                .num_args(0)
+                .help("在缓存目录中保留 pip 的下载缓存，多次构建之间共享"),
+        )
+        .arg(
+            clap::Arg::new("find-links")
+                .long("find-links")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .action(clap::ArgAction::Append)
+                .help("额外查找依赖包的本地目录或 URL，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("no-index")
+                .long("no-index")
+                .global(true)
+                .num_args(0)
+                .help("不访问任何索引，只从 --find-links 中查找依赖包，用于离线构建"),
+        )
+        .arg(
+            clap::Arg::new("host-python")
+                .long("host-python")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .help("wheelhouse 使用的本机 Python，默认自动查找"),
+        )
+        .arg(
+            clap::Arg::new("installer")
+                .long("installer")
+                .global(true)
+                .num_args(1)
+                .value_parser(["pip", "uv", "native"])
+                .help("安装依赖包使用的后端：pip（默认，在运行时中运行），uv（在本机运行），native（内置，不运行 python.exe，只能安装 wheel）"),
+        )
+        .arg(
+            clap::Arg::new("check")
+                .long("check")
+                .global(true)
+                .num_args(1)
+                .value_parser(["ignore", "warn", "fail"])
+                .help("安装后检查依赖是否满足，发现问题时：ignore（不检查），warn（默认，输出警告），fail（构建失败）"),
+        )
+        .arg(
+            clap::Arg::new("uv")
+                .long("uv")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .help("uv 可执行文件路径，默认从 PATH 中查找"),
+        )
         .arg(
             clap::Arg::new("keep-scripts")
                 .long("keep-scripts")
+                .global(true)
                 .num_args(0)
                 .help("保留 Scripts 目录"),
         )
         .arg(
             clap::Arg::new("keep-dist-info")
                 .long("keep-dist-info")
+                .global(true)
                 .num_args(0)
-                .help("保留 dist-info 目录，删除此目录后将无法再通过 pip 管理依赖"),
+                .help("保留 dist-info 目录，没有指定 --output-dir 时删除此目录后将无法再通过 pip 管理依赖"),
+        )
+        .arg(
+            clap::Arg::new("minimal-dist-info")
+                .long("minimal-dist-info")
+                .global(true)
+                .num_args(0)
+                .help("不保留 dist-info 时为每个包保留最小的元数据，供 importlib.metadata 读取版本和入口点"),
+        )
+        .arg(
+            clap::Arg::new("keep-dist-info-for")
+                .long("keep-dist-info-for")
+                .global(true)
+                .num_args(1)
+                .value_name("package")
+                .action(clap::ArgAction::Append)
+                .help("始终保留此包完整的 dist-info，可多次指定"),
         )
         .arg(
             clap::Arg::new("keep-pip")
                 .long("keep-pip")
+                .global(true)
                 .num_args(0)
                 .help("保留 pip、setuptools、wheel 依赖包"),
         )
         .arg(
             clap::Arg::new("optimize")
                 .long("optimize")
+                .global(true)
                 .num_args(1)
                 .value_name("level")
                 .help(
                     "优化编译级别：0（不优化），1（删除断言，关闭调试），2（同时删除文档字符串）",
                 ),
         )
+        .arg(
+            clap::Arg::new("recompile-stdlib")
+                .long("recompile-stdlib")
+                .global(true)
+                .num_args(0)
+                .help("按优化编译级别重新编译标准库 pythonXY.zip，需要下载同版本的源码包"),
+        )
+        .arg(
+            clap::Arg::new("compile-jobs")
+                .long("compile-jobs")
+                .global(true)
+                .num_args(1)
+                .value_name("n")
+                .help("并行编译的进程数，默认按 CPU 数量"),
+        )
+        .arg(
+            clap::Arg::new("compile-failure")
+                .long("compile-failure")
+                .global(true)
+                .num_args(1)
+                .value_parser(["ignore", "warn", "fail"])
+                .help("有文件编译失败时：ignore（忽略），warn（默认，输出警告），fail（构建失败）"),
+        )
+        .arg(
+            clap::Arg::new("invalidation-mode")
+                .long("invalidation-mode")
+                .global(true)
+                .num_args(1)
+                .value_parser(["timestamp", "checked-hash", "unchecked-hash"])
+                .help(".pyc 的失效检查方式，默认为 timestamp，基于哈希的方式可以得到相同的 .pyc"),
+        )
+        .arg(
+            clap::Arg::new("source-date-epoch")
+                .long("source-date-epoch")
+                .global(true)
+                .num_args(1)
+                .value_name("timestamp")
+                .help("可重现构建的时间戳，文件的修改时间不晚于此时间，默认读取环境变量 SOURCE_DATE_EPOCH"),
+        )
+        .arg(
+            clap::Arg::new("no-compile")
+                .long("no-compile")
+                .global(true)
+                .num_args(1)
+                .value_name("pattern")
+                .action(clap::ArgAction::Append)
+                .help("不编译匹配的包或路径（glob），原样保留，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("keep-source-cached")
+                .long("keep-source-cached")
+                .global(true)
+                .num_args(1)
+                .value_name("pattern")
+                .action(clap::ArgAction::Append)
+                .help("保留匹配的包或路径（glob）的源文件，同时在 __pycache__ 中生成 .pyc，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("keep-source")
+                .long("keep-source")
+                .global(true)
+                .num_args(1)
+                .value_name("pattern")
+                .action(clap::ArgAction::Append)
+                .help("保留匹配的包或路径（glob）的源文件，不生成 .pyc，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("disable-feature")
+                .long("disable-feature")
+                .global(true)
+                .num_args(1)
+                .value_name("feature")
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    features().into_iter().map(|(name, description)| {
+                        clap::builder::PossibleValue::new(name).help(description)
+                    }),
+                ))
+                .action(clap::ArgAction::Append)
+                .help("删除此功能对应的扩展模块、DLL 和标准库中的包，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("max-size")
+                .long("max-size")
+                .global(true)
+                .num_args(1)
+                .value_name("size")
+                .help("运行时的大小上限，如 50MB，构建完成后超过时失败"),
+        )
+        .arg(
+            clap::Arg::new("entry-script")
+                .long("entry-script")
+                .global(true)
+                .num_args(1)
+                .value_name("file")
+                .help("应用的入口脚本，分析导入后删除没有用到的模块"),
+        )
+        .arg(
+            clap::Arg::new("trace-run")
+                .long("trace-run")
+                .global(true)
+                .num_args(0)
+                .help("运行入口脚本记录实际导入的模块，默认静态分析"),
+        )
+        .arg(
+            clap::Arg::new("keep-module")
+                .long("keep-module")
+                .global(true)
+                .num_args(1)
+                .value_name("module")
+                .action(clap::ArgAction::Append)
+                .help("始终保留此模块及其子模块，用于动态导入，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("shake-report")
+                .long("shake-report")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .help("导入分析报告的保存路径，默认保存到缓存目录"),
+        )
+        .arg(
+            clap::Arg::new("strip")
+                .long("strip")
+                .global(true)
+                .num_args(0)
+                .help("编译后删除测试、文档、类型存根、C 源文件和其他版本的 .pyc 等不需要的文件"),
+        )
+        .arg(
+            clap::Arg::new("strip-rules")
+                .long("strip-rules")
+                .global(true)
+                .num_args(1)
+                .value_name("file")
+                .help("额外的删除规则文件，格式与 .gitignore 相同，可以用 [包名] 限定作用的包"),
+        )
+        .arg(
+            clap::Arg::new("zip-site-packages")
+                .long("zip-site-packages")
+                .global(true)
+                .num_args(0)
+                .help("把纯 Python 包打包为 Lib/site-packages.zip"),
+        )
+        .arg(
+            clap::Arg::new("zip-package")
+                .long("zip-package")
+                .global(true)
+                .num_args(1)
+                .value_name("package")
+                .action(clap::ArgAction::Append)
+                .help("只把此包打包为 zip，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("project")
+                .long("project")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .help("项目目录或 pyproject.toml 文件，将安装其中声明的依赖"),
+        )
+        .arg(
+            clap::Arg::new("extra")
+                .long("extra")
+                .global(true)
+                .num_args(1)
+                .value_name("name")
+                .action(clap::ArgAction::Append)
+                .help("同时安装项目中指定的 optional-dependencies，可多次指定"),
+        )
+        .arg(
+            clap::Arg::new("install-project")
+                .long("install-project")
+                .global(true)
+                .num_args(0)
+                .requires("project")
+                .help("将项目本身构建为 wheel 并安装"),
+        )
+        .arg(
+            clap::Arg::new("lock-file")
+                .long("lock-file")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .help("锁文件路径，默认为 <当前目录>\\pyembed.lock"),
+        )
+        .arg(
+            clap::Arg::new("locked")
+                .long("locked")
+                .global(true)
+                .num_args(0)
+                .help("严格按锁文件安装，锁文件与当前配置不一致时报错"),
+        )
+        .arg(
+            clap::Arg::new("import-lock")
+                .long("import-lock")
+                .global(true)
+                .num_args(1)
+                .value_name("path")
+                .conflicts_with("locked")
+                .help("导入 poetry.lock、uv.lock 或 Pipfile.lock，按目标环境安装其中锁定的版本"),
+        )
         .arg(
             clap::Arg::new("PACKAGES")
                 .index(1)
                 .num_args(0..)
                 .help("要安装的 pip 依赖包"),
         )
+        .arg(
+            clap::Arg::new("report-json")
+                .long("report-json")
+                .num_args(1)
+                .value_name("path")
+                .help("把构建结果以 JSON 对象保存到指定文件，安装的包位于 installed 字段（以前的版本直接保存为数组），另有 compile_failures、size 字段"),
+        )
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            clap::Command::new("wheelhouse")
+                .about("按目标平台预先下载所需的全部 wheel，之后可配合 --find-links、--no-index 离线构建")
+                .arg(clap::Arg::new("DIR").required(true).help("保存 wheel 的目录"))
+                .arg(
+                    clap::Arg::new("PACKAGES")
+                        .num_args(0..)
+                        .help("要下载的 pip 依赖包"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("resolve")
+                .about("不运行 pip，直接从索引和 --find-links 中解析依赖，列出要下载的 wheel")
+                .arg(
+                    clap::Arg::new("output")
+                        .long("output")
+                        .num_args(1)
+                        .value_name("path")
+                        .help("把解析结果以 JSON 格式保存到指定文件"),
+                )
+                .arg(
+                    clap::Arg::new("PACKAGES")
+                        .num_args(0..)
+                        .help("要解析的 pip 依赖包"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("add")
+                .about("在已经构建好的运行时中添加依赖包，需要构建时使用 --keep-dist-info")
+                .arg(
+                    clap::Arg::new("PACKAGES")
+                        .num_args(1..)
+                        .required(true)
+                        .help("要添加的 pip 依赖包"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("remove")
+                .about("从已经构建好的运行时中删除依赖包，需要构建时使用 --keep-dist-info")
+                .arg(
+                    clap::Arg::new("PACKAGES")
+                        .num_args(1..)
+                        .required(true)
+                        .help("要删除的依赖包名称"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("upgrade")
+                .about("升级已经构建好的运行时中的依赖包，需要构建时使用 --keep-dist-info")
+                .arg(
+                    clap::Arg::new("PACKAGES")
+                        .num_args(1..)
+                        .required(true)
+                        .help("要升级的依赖包"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("clear-cache")
+                .about("清除缓存目录中 pip、uv 的下载缓存和内置安装器下载的 wheel"),
+        )
         .get_matches();
+    // 选项都是 global 的，子命令的 ArgMatches 中同样包含这些选项
+    let (subcommand, matches) = match matches.subcommand() {
+        Some((name, sub)) => (Some(name.to_string()), sub.clone()),
+        None => (None, matches),
+    };
     let mut config = Config::default();
     if let Some(mut s) = matches.get_raw("dir") {
         let mut p = std::path::PathBuf::from(s.next().unwrap());
@@ -109,6 +511,13 @@ async fn main() -> Result<()> {
         }
         config.dir = p;
     }
+    if let Some(mut s) = matches.get_raw("output-dir") {
+        let mut p = std::path::PathBuf::from(s.next().unwrap());
+        if p.is_relative() {
+            p = std::env::current_dir()?.join(p);
+        }
+        config.output_dir = Some(p);
+    }
     if let Some(mut s) = matches.get_raw("cachedir") {
         let mut p = std::path::PathBuf::from(s.next().unwrap());
         if p.is_relative() {
@@ -133,17 +542,168 @@ async fn main() -> Result<()> {
     if let Some(s) = matches.get_one::<String>("pip-mirror") {
         config.pip_mirror = s.to_string();
     }
+    config.extra_index_urls = matches
+        .get_many::<String>("extra-index-url")
+        .unwrap_or_default()
+        .map(|s| s.to_string())
+        .collect();
+    config.trusted_hosts = matches
+        .get_many::<String>("trusted-host")
+        .unwrap_or_default()
+        .map(|s| s.to_string())
+        .collect();
+    config.pip_args = matches
+        .get_many::<String>("pip-arg")
+        .unwrap_or_default()
+        .map(|s| s.to_string())
+        .collect();
+    config.pip_config = matches
+        .get_one::<String>("pip-config")
+        .map(std::path::PathBuf::from);
+    config.pip_cache = matches.get_flag("pip-cache");
+    config.find_links = matches
+        .get_many::<String>("find-links")
+        .unwrap_or_default()
+        .map(|s| s.to_string())
+        .collect();
+    config.no_index = matches.get_flag("no-index");
+    if let Some(s) = matches.get_one::<String>("host-python") {
+        config.host_python = s.to_string();
+    }
+    if let Some(s) = matches.get_one::<String>("installer") {
+        config.installer = match s.as_str() {
+            "uv" => Backend::Uv,
+            "native" => Backend::Native,
+            _ => Backend::Pip,
+        };
+    }
+    if let Some(s) = matches.get_one::<String>("check") {
+        config.check = match s.as_str() {
+            "ignore" => CheckPolicy::Ignore,
+            "fail" => CheckPolicy::Fail,
+            _ => CheckPolicy::Warn,
+        };
+    }
+    if let Some(s) = matches.get_one::<String>("uv") {
+        config.uv = s.to_string();
+    }
     config.keep_scripts = matches.get_flag("keep-scripts");
     config.keep_dist_info = matches.get_flag("keep-dist-info");
+    config.minimal_dist_info = matches.get_flag("minimal-dist-info");
+    config.keep_dist_info_for = matches
+        .get_many::<String>("keep-dist-info-for")
+        .unwrap_or_default()
+        .map(|s| s.trim().to_string())
+        .collect();
     config.keep_pip = matches.get_flag("keep-pip");
+    // clear-cache 子命令没有 PACKAGES 参数
     config.packages = matches
-        .get_many::<String>("PACKAGES")
+        .try_get_many::<String>("PACKAGES")
+        .ok()
+        .flatten()
         .unwrap_or_default()
         .map(|s| s.trim().to_string())
         .collect();
     if let Some(s) = matches.get_one::<String>("optimize") {
         config.optimize = s.parse()?;
     }
+    config.recompile_stdlib = matches.get_flag("recompile-stdlib");
+    if let Some(s) = matches.get_one::<String>("compile-jobs") {
+        config.compile_jobs = s.parse()?;
+    }
+    if let Some(s) = matches.get_one::<String>("invalidation-mode") {
+        config.invalidation_mode = match s.as_str() {
+            "checked-hash" => InvalidationMode::CheckedHash,
+            "unchecked-hash" => InvalidationMode::UncheckedHash,
+            _ => InvalidationMode::Timestamp,
+        };
+    }
+    match matches.get_one::<String>("source-date-epoch") {
+        Some(s) => config.source_date_epoch = Some(s.parse()?),
+        None => {
+            if let Ok(s) = std::env::var("SOURCE_DATE_EPOCH") {
+                config.source_date_epoch = Some(s.trim().parse()?);
+            }
+        }
+    }
+    if let Some(s) = matches.get_one::<String>("compile-failure") {
+        config.compile_failure = match s.as_str() {
+            "ignore" => CheckPolicy::Ignore,
+            "fail" => CheckPolicy::Fail,
+            _ => CheckPolicy::Warn,
+        };
+    }
+    // 按 --no-compile、--keep-source-cached、--keep-source 的顺序匹配
+    for (name, mode) in [
+        ("no-compile", SourceMode::Skip),
+        ("keep-source-cached", SourceMode::Cached),
+        ("keep-source", SourceMode::Source),
+    ] {
+        for pattern in matches.get_many::<String>(name).unwrap_or_default() {
+            config.source_rules.push(SourceRule {
+                pattern: pattern.trim().to_string(),
+                mode,
+            });
+        }
+    }
+    config.disabled_features = matches
+        .get_many::<String>("disable-feature")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+    if let Some(s) = matches.get_one::<String>("max-size") {
+        config.max_size = Some(parse_size(s)?);
+    }
+    if let Some(s) = matches.get_one::<String>("entry-script") {
+        config.entry_script = Some(std::env::current_dir()?.join(s));
+    }
+    config.trace_run = matches.get_flag("trace-run");
+    config.keep_modules = matches
+        .get_many::<String>("keep-module")
+        .unwrap_or_default()
+        .map(|s| s.trim().to_string())
+        .collect();
+    if let Some(s) = matches.get_one::<String>("shake-report") {
+        config.shake_report = Some(std::env::current_dir()?.join(s));
+    }
+    config.strip = matches.get_flag("strip");
+    if let Some(s) = matches.get_one::<String>("strip-rules") {
+        config.strip_rules = Some(std::env::current_dir()?.join(s));
+    }
+    config.zip_site_packages = matches.get_flag("zip-site-packages");
+    config.zip_packages = matches
+        .get_many::<String>("zip-package")
+        .unwrap_or_default()
+        .map(|s| s.trim().to_string())
+        .collect();
+    if let Some(mut s) = matches.get_raw("project") {
+        let mut p = std::path::PathBuf::from(s.next().unwrap());
+        if p.is_relative() {
+            p = std::env::current_dir()?.join(p);
+        }
+        config.project = Some(p);
+    }
+    config.extras = matches
+        .get_many::<String>("extra")
+        .unwrap_or_default()
+        .map(|s| s.trim().to_string())
+        .collect();
+    config.install_project = matches.get_flag("install-project");
+    if let Some(mut s) = matches.get_raw("lock-file") {
+        let mut p = std::path::PathBuf::from(s.next().unwrap());
+        if p.is_relative() {
+            p = std::env::current_dir()?.join(p);
+        }
+        config.lock_file = p;
+    }
+    config.locked = matches.get_flag("locked");
+    if let Some(mut s) = matches.get_raw("import-lock") {
+        let mut p = std::path::PathBuf::from(s.next().unwrap());
+        if p.is_relative() {
+            p = std::env::current_dir()?.join(p);
+        }
+        config.import_lock = Some(p);
+    }
 
     let last_len = std::cell::Cell::new(0);
     let simple_progress = |total: i64, read: i64| {
@@ -172,13 +732,65 @@ async fn main() -> Result<()> {
         std::io::stdout().flush().unwrap();
     };
 
-    if atty::is(atty::Stream::Stdout) {
-        run(&config, &simple_progress).await
+    let progress_callback: &dyn Fn(i64, i64) = if atty::is(atty::Stream::Stdout) {
+        &simple_progress
     } else {
-        run(&config, &|_: i64, _: i64| {}).await
+        &|_: i64, _: i64| {}
+    };
+    match subcommand.as_deref() {
+        Some("wheelhouse") => {
+            let dir = std::path::PathBuf::from(matches.get_one::<String>("DIR").unwrap());
+            wheelhouse(&config, &dir, progress_callback).await
+        }
+        Some("add") => add(&config, &config.packages, progress_callback).await,
+        Some("remove") => remove(&config, &config.packages, progress_callback).await,
+        Some("upgrade") => upgrade(&config, &config.packages, progress_callback).await,
+        Some("clear-cache") => clear_cache(&config),
+        Some("resolve") => {
+            let output = matches
+                .get_one::<String>("output")
+                .map(std::path::PathBuf::from);
+            resolve(&config, output.as_deref(), progress_callback).await
+        }
+        _ => {
+            let report = run(&config, progress_callback).await?;
+            log::info!("安装结果");
+            for line in table(&report.installed) {
+                log::info!("{}", line);
+            }
+            if !report.compile_failures.is_empty() {
+                log::info!("编译失败的文件（已保留源文件）");
+                for i in report.compile_failures.iter() {
+                    log::info!("{}：{}", i.file, i.error);
+                }
+            }
+            log::info!("磁盘占用");
+            for line in size_table(&report.size) {
+                log::info!("{}", line);
+            }
+            if let Some(path) = matches.get_one::<String>("report-json") {
+                std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+                log::info!("安装结果已保存到：{}", path);
+            }
+            Ok(())
+        }
     }
 }
 
+// 文件大小，如 1048576、500KB、50MB、1.5G，单位为 1024
+fn parse_size(s: &str) -> Result<u64> {
+    let caps = regex_find(r"(?i)^\s*(\d+(?:\.\d+)?)\s*([KMG]?)B?\s*$", s)
+        .ok_or(format!("无效的大小：{}", s))?;
+    let value: f64 = caps.get(1).unwrap().as_str().parse()?;
+    let unit = match caps.get(2).unwrap().as_str().to_ascii_uppercase().as_str() {
+        "K" => 1u64 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => 1,
+    };
+    Ok((value * unit as f64) as u64)
+}
+
 fn regex_find<'a>(re: &str, text: &'a str) -> Option<regex::Captures<'a>> {
     if let Ok(re) = regex::RegexBuilder::new(re)
         .dot_matches_new_line(true)
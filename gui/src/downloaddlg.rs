@@ -1,4 +1,4 @@
-use crate::{dialog, logger, resources, run, Config};
+use crate::{dialog, logger, resources, run, size_table, table, BuildReport, Config};
 use winapi::shared::minwindef::LRESULT;
 use winapi::shared::minwindef::{LPARAM, UINT, WPARAM};
 use winapi::shared::windef::HWND;
@@ -87,7 +87,7 @@ impl<'a> Drop for DownloadProc<'a> {
 
 enum Msg {
     Progress(i64, i64),
-    Result(Result<(), String>),
+    Result(Result<BuildReport, String>),
 }
 
 impl<'a> dialog::DialogProc for DownloadProc<'a> {
@@ -146,10 +146,25 @@ impl<'a> dialog::DialogProc for DownloadProc<'a> {
                         self.set_progress(dlg, 100, 0);
                         self.set_progress(dlg, 100, 100);
                         match r {
-                            Ok(_) => {
+                            Ok(report) => {
+                                info!("安装结果");
+                                for line in table(&report.installed) {
+                                    info!("{}", line);
+                                }
+                                info!("磁盘占用");
+                                for line in size_table(&report.size) {
+                                    info!("{}", line);
+                                }
+                                let mut s = format!("完成！共安装 {} 个包", report.installed.len());
+                                if !report.compile_failures.is_empty() {
+                                    s += &format!(
+                                        "，{} 个文件编译失败，已保留源文件",
+                                        report.compile_failures.len()
+                                    );
+                                }
                                 dlg.set_item_text(resources::IDC_BTN_EXIT, "完成");
                                 dlg.message_box(
-                                    "完成！",
+                                    &s,
                                     crate::APP_NAME,
                                     winapi::um::winuser::MB_ICONINFORMATION,
                                 );
@@ -9,7 +9,7 @@ mod logger;
 mod maindlg;
 mod resources;
 
-use pyembed_downloader::{run, Config};
+use pyembed_downloader::{run, size_table, table, BuildReport, Config};
 
 static APP_NAME: &'static str = "pyembed_downloader";
 
@@ -26,7 +26,10 @@ impl<'a> dialog::DialogProc for MainProc<'a> {
         );
         // 设置控件初始值
         dlg.set_item_text(resources::IDC_EDT_DIR, self.config.dir.to_str().unwrap());
-        dlg.set_item_text(resources::IDC_EDT_CACHE_DIR, self.config.cache_dir.to_str().unwrap());
+        dlg.set_item_text(
+            resources::IDC_EDT_CACHE_DIR,
+            self.config.cache_dir.to_str().unwrap(),
+        );
         dlg.set_item_text(resources::IDC_EDT_VER, &self.config.pyver);
         dlg.set_enable(resources::IDC_EDT_VER, self.config.pyver != "latest");
         dlg.set_check(resources::IDC_CHK_VER, self.config.pyver != "latest");
@@ -48,7 +51,11 @@ impl<'a> dialog::DialogProc for MainProc<'a> {
             );
         }
         dlg.set_item_text(resources::IDC_CBO_PIP_MIRROR, &self.config.pip_mirror);
-        for i in ["", "https://mirrors.huaweicloud.com/python", "https://registry.npmmirror.com/-/binary/python"] {
+        for i in [
+            "",
+            "https://mirrors.huaweicloud.com/python",
+            "https://registry.npmmirror.com/-/binary/python",
+        ] {
             dlg.send_item_message(
                 resources::IDC_CBO_PYTHON_MIRROR,
                 winuser::CB_ADDSTRING,
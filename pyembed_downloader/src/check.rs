@@ -0,0 +1,116 @@
+// 与 pip check 相同的依赖一致性检查，直接读取 METADATA，不需要运行目标 python.exe
+// 与 pip check 一样只检查不带 extra 的依赖
+
+use crate::markers::Environment;
+use crate::pep440::Version;
+use crate::pyproject::normalize_name;
+use crate::requirement::Requirement;
+use crate::Result;
+
+// removed 中的包会在清理时删除，按未安装处理
+pub(crate) fn check(
+    site: &std::path::Path,
+    env: &Environment,
+    removed: &[&str],
+) -> Result<Vec<String>> {
+    let installed: Vec<crate::metadata::Metadata> = crate::metadata::installed(site)?
+        .into_iter()
+        .map(|i| i.1)
+        .filter(|i| !removed.contains(&normalize_name(&i.name).as_str()))
+        .collect();
+    let mut problems = vec![];
+    for dist in installed.iter() {
+        for i in dist.requires_dist.iter() {
+            let req: Requirement = match i.parse() {
+                Ok(req) => req,
+                Err(_) => {
+                    problems.push(format!(
+                        "{} {} 的依赖声明无效：{}",
+                        dist.name, dist.version, i
+                    ));
+                    continue;
+                }
+            };
+            // 环境标记无法解析时与依赖声明无效一样作为问题报告，由检查策略决定是否中止
+            match req.applies(env, &[]) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    problems.push(format!(
+                        "{} {} 的环境标记无效：{}（{}）",
+                        dist.name, dist.version, i, e
+                    ));
+                    continue;
+                }
+            }
+            let dep = installed
+                .iter()
+                .find(|i| normalize_name(&i.name) == req.name);
+            match dep {
+                None => problems.push(format!(
+                    "{} {} 依赖 {}，但没有安装",
+                    dist.name, dist.version, req.name
+                )),
+                Some(dep) => {
+                    // 无法解析的版本号不做比较
+                    let version: Version = match dep.version.parse() {
+                        Ok(version) => version,
+                        Err(_) => continue,
+                    };
+                    if !req.specifiers.contains(&version) {
+                        problems.push(format!(
+                            "{} {} 依赖 {}{}，但安装的是 {} {}",
+                            dist.name,
+                            dist.version,
+                            req.name,
+                            req.specifiers,
+                            dep.name,
+                            dep.version
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_padded_pin() {
+        let site = std::env::temp_dir().join(format!("pyembed-check-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&site);
+        let install = |name: &str, version: &str, requires: &[&str]| {
+            let dist_info = site.join(format!("{}-{}.dist-info", name, version));
+            std::fs::create_dir_all(&dist_info).unwrap();
+            let mut metadata = format!(
+                "Metadata-Version: 2.1\nName: {}\nVersion: {}\n",
+                name, version
+            );
+            for i in requires {
+                metadata += &format!("Requires-Dist: {}\n", i);
+            }
+            std::fs::write(dist_info.join("METADATA"), metadata).unwrap();
+        };
+        install(
+            "app",
+            "1.0",
+            &["foo==1.0", "bar!=2.0", "baz; python_version < '3'"],
+        );
+        install("foo", "1.0.0", &[]);
+        install("bar", "2.0.0", &[]);
+        let env = Environment::new("3.11.7", false);
+
+        // foo 1.0.0 满足 ==1.0，bar 2.0.0 不满足 !=2.0，baz 的环境标记不满足
+        let problems = check(&site, &env, &[]).unwrap();
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].contains("bar!=2.0"), "{:?}", problems);
+
+        let problems = check(&site, &env, &["foo"]).unwrap();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
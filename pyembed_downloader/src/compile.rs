@@ -0,0 +1,288 @@
+// 把 site-packages 中的 .py 编译为 .pyc 并删除源文件
+// 由本程序遍历目录，把文件分给多个 Python 工作进程并行编译
+// 工作进程从标准输入逐行读取 JSON：{"file": "pkg/mod.py", "keep_source": false}
+// 每编译一个文件输出一行 JSON：
+// {"file": "pkg/mod.py", "status": "ok"} 或 {"file": "pkg/mod.py", "status": "error", "error": "..."}
+
+use crate::config::{Config, SourceMode, SourceRule};
+use crate::pyproject::normalize_name;
+use crate::report::CompileFailure;
+use crate::Result;
+
+const WORKER: &str = r#"
+import sys
+import os
+import json
+import py_compile
+
+SITEDIR = sys.argv[1]
+OPTIMIZE = int(sys.argv[2])
+INVALIDATION_MODE = py_compile.PycInvalidationMode[sys.argv[3].upper().replace("-", "_")]
+
+for line in sys.stdin:
+    if not line.strip():
+        continue
+    task = json.loads(line)
+    shortname = task["file"]
+    keep_source = task["keep_source"]
+    fullname = os.path.join(SITEDIR, shortname)
+    # 保留源文件时写入 __pycache__，否则写在源文件旁边作为无源码的 .pyc
+    cfile = None if keep_source else fullname + "c"
+    try:
+        py_compile.compile(
+            fullname,
+            cfile=cfile,
+            dfile=shortname,
+            doraise=True,
+            optimize=OPTIMIZE,
+            invalidation_mode=INVALIDATION_MODE,
+        )
+    except py_compile.PyCompileError as e:
+        event = {"file": shortname, "status": "error", "error": f"{e.exc_type_name}: {e.exc_value}"}
+    except Exception as e:
+        event = {"file": shortname, "status": "error", "error": f"{type(e).__name__}: {e}"}
+    else:
+        if not keep_source:
+            os.remove(fullname)
+        event = {"file": shortname, "status": "ok"}
+    print(json.dumps(event, ensure_ascii=False), flush=True)
+"#;
+
+#[derive(Debug, Default)]
+pub(crate) struct Summary {
+    pub compiled: usize,
+    // 编译失败的文件，这些文件的源码会保留
+    pub failed: Vec<CompileFailure>,
+    pub found_pyd: bool,
+}
+
+// paths 为 site-packages 中要编译的文件或目录，为空时编译整个 site-packages
+// new_python 每次调用返回一个新的 Python 命令
+pub(crate) fn compile(
+    new_python: &dyn Fn() -> std::process::Command,
+    config: &Config,
+    dir: &std::path::Path,
+    paths: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<Summary> {
+    let site = dir.join("Lib").join("site-packages");
+    let rules = Rules::new(&config.source_rules)?;
+    let mut summary = Summary::default();
+    let mut files = vec![];
+    let targets = if paths.is_empty() {
+        vec![String::new()]
+    } else {
+        paths.to_vec()
+    };
+    for i in targets {
+        let path = site.join(&i);
+        if rules.find(&relative(&site, &path)) == Some(SourceMode::Skip) {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&site, &path, &rules, &mut files, &mut summary)?;
+        } else if path.is_file() {
+            check_file(&site, &path, &rules, &mut files, &mut summary);
+        }
+    }
+    if files.is_empty() {
+        return Ok(summary);
+    }
+
+    let jobs = if config.compile_jobs == 0 {
+        std::thread::available_parallelism().map_or(1, |i| i.get())
+    } else {
+        config.compile_jobs
+    };
+    let jobs = jobs.min(files.len());
+    info!("编译 {} 个文件，{} 个进程", files.len(), jobs);
+    let mut shards = vec![vec![]; jobs];
+    for (n, i) in files.iter().enumerate() {
+        shards[n % jobs].push(i.clone());
+    }
+
+    let (sender, receiver) = std::sync::mpsc::channel::<String>();
+    let mut workers = vec![];
+    for shard in shards {
+        let mut cmd = new_python();
+        cmd.args(["-c", WORKER]);
+        cmd.arg(&site);
+        cmd.arg(config.optimize.to_string());
+        cmd.arg(config.invalidation_mode.as_str());
+        cmd.stdin(std::process::Stdio::piped());
+        let mut process = cmd.spawn()?;
+        let mut stdin = process.stdin.take().unwrap();
+        let stdout = process.stdout.take().unwrap();
+        let stderr = process.stderr.take().unwrap();
+        let sender = sender.clone();
+        let t1 = std::thread::spawn(move || {
+            use std::io::BufRead;
+            for line in std::io::BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => sender.send(line).unwrap(),
+                    Err(_) => break,
+                }
+            }
+        });
+        let t2 = crate::read_to_log(stderr, log::Level::Error);
+        let t3 = std::thread::spawn(move || {
+            use std::io::Write;
+            for (file, keep_source) in shard {
+                let task = serde_json::json!({"file": file, "keep_source": keep_source});
+                if writeln!(stdin, "{}", task).is_err() {
+                    break;
+                }
+            }
+        });
+        workers.push((process, [t1, t2, t3]));
+    }
+    drop(sender);
+
+    let total = files.len() as i64;
+    let mut done = 0;
+    progress_callback(total, 0);
+    for line in receiver {
+        let event: serde_json::Value = match serde_json::from_str(&line) {
+            Ok(event) => event,
+            Err(_) => {
+                info!("{}", line);
+                continue;
+            }
+        };
+        let file = event["file"].as_str().unwrap_or_default().to_string();
+        if event["status"] == "ok" {
+            summary.compiled += 1;
+        } else {
+            let error = event["error"].as_str().unwrap_or_default().to_string();
+            summary.failed.push(CompileFailure { file, error });
+        }
+        done += 1;
+        progress_callback(total, done);
+    }
+    progress_callback(-1, -1);
+
+    let mut result: Result<()> = Ok(());
+    for (mut process, threads) in workers {
+        let status = process.wait()?;
+        for i in threads {
+            i.join().unwrap();
+        }
+        if !status.success() {
+            result = Err(format!("编译失败 [{}]", status).into());
+        }
+    }
+    result?;
+
+    info!(
+        "编译完成：成功 {} 个，失败 {} 个",
+        summary.compiled,
+        summary.failed.len()
+    );
+    Ok(summary)
+}
+
+// 删除旧的 __pycache__，跳过 dist-info 和匹配 Skip 规则的文件和目录
+fn walk(
+    site: &std::path::Path,
+    dir: &std::path::Path,
+    rules: &Rules,
+    files: &mut Vec<(String, bool)>,
+    summary: &mut Summary,
+) -> Result<()> {
+    let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(dir)?
+        .map(|i| i.map(|i| i.path()))
+        .collect::<std::io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+        if rules.find(&relative(site, &path)) == Some(SourceMode::Skip) {
+            continue;
+        }
+        if path.is_dir() {
+            if name == "__pycache__" {
+                info!("删除：{}", relative(site, &path));
+                std::fs::remove_dir_all(&path)?;
+            } else if !name.ends_with(".dist-info") {
+                walk(site, &path, rules, files, summary)?;
+            }
+        } else if path.is_file() {
+            check_file(site, &path, rules, files, summary);
+        } else {
+            info!("未知文件类型：{}", relative(site, &path));
+        }
+    }
+    Ok(())
+}
+
+fn check_file(
+    site: &std::path::Path,
+    path: &std::path::Path,
+    rules: &Rules,
+    files: &mut Vec<(String, bool)>,
+    summary: &mut Summary,
+) {
+    let name = path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_ascii_lowercase();
+    if name.ends_with(".py") {
+        let shortname = relative(site, path);
+        match rules.find(&shortname) {
+            None => files.push((shortname, false)),
+            Some(SourceMode::Cached) => files.push((shortname, true)),
+            Some(_) => {}
+        }
+    } else if name.ends_with(".pyd") {
+        summary.found_pyd = true;
+    }
+}
+
+fn relative(site: &std::path::Path, path: &std::path::Path) -> String {
+    path.strip_prefix(site)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+struct Rules {
+    rules: Vec<(Option<globset::GlobMatcher>, String, SourceMode)>,
+}
+
+impl Rules {
+    fn new(rules: &[SourceRule]) -> Result<Self> {
+        let mut result = vec![];
+        for i in rules {
+            let glob = if i.pattern.contains(['/', '*', '?', '[']) {
+                let glob = globset::GlobBuilder::new(&i.pattern)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| format!("无效的匹配规则 {}：{}", i.pattern, e))?;
+                Some(glob.compile_matcher())
+            } else {
+                None
+            };
+            result.push((glob, normalize_name(&i.pattern), i.mode));
+        }
+        Ok(Rules { rules: result })
+    }
+
+    // path 为相对 site-packages 的路径
+    fn find(&self, path: &str) -> Option<SourceMode> {
+        let path = path.replace('\\', "/");
+        let top = path.split('/').next().unwrap_or_default();
+        let top = top.strip_suffix(".py").unwrap_or(top);
+        let top = normalize_name(top);
+        self.rules
+            .iter()
+            .find(|(glob, name, _)| match glob {
+                Some(glob) => glob.is_match(&path),
+                None => *name == top,
+            })
+            .map(|i| i.2)
+    }
+}
@@ -1,8 +1,73 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    // 在运行时中运行 pip，只能在 Windows 上使用
+    Pip,
+    // 在本机运行 uv，非 Windows 系统上使用 --target 安装
+    Uv,
+    // 使用内置的 wheel 安装器和依赖解析，不运行目标 python.exe，可以在非 Windows 系统上构建运行时
+    // 只能安装 wheel，从 find_links 中的本地目录和索引中查找
+    Native,
+}
+
+// 安装后依赖一致性检查、编译发现问题时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckPolicy {
+    // 不检查
+    Ignore,
+    // 只输出警告
+    Warn,
+    // 构建失败
+    Fail,
+}
+
+// 编译时对匹配的文件的处理方式，默认编译为 .pyc 并删除源文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceMode {
+    // 保留 .py 源文件，不生成 .pyc
+    Source,
+    // 保留 .py 源文件，同时在 __pycache__ 中生成 .pyc
+    Cached,
+    // 不做任何处理，原有的 __pycache__ 也保留
+    Skip,
+}
+
+// .pyc 的失效检查方式，与 py_compile.PycInvalidationMode 相同
+// 基于哈希的方式不记录源文件的修改时间，相同的输入得到相同的 .pyc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidationMode {
+    Timestamp,
+    CheckedHash,
+    UncheckedHash,
+}
+
+impl InvalidationMode {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            InvalidationMode::Timestamp => "timestamp",
+            InvalidationMode::CheckedHash => "checked-hash",
+            InvalidationMode::UncheckedHash => "unchecked-hash",
+        }
+    }
+}
+
+// pattern 含有 /、*、?、[ 时为 glob，匹配相对 site-packages 的路径，如 numba/**
+// 否则为包名，匹配顶层的包或模块，如 numba
+#[derive(Debug, Clone)]
+pub struct SourceRule {
+    pub pattern: String,
+    pub mode: SourceMode,
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     // 工作目录，默认为 <当前目录>\pyembed_runtime\
     pub dir: std::path::PathBuf,
 
+    // 输出目录，为空时直接在工作目录中编译和清理
+    // 指定后工作目录作为完整的构建目录保留 pip、Scripts 和 dist-info，可以继续用 pip 管理
+    // 每次构建后把工作目录复制到输出目录，再在输出目录中编译和清理
+    pub output_dir: Option<std::path::PathBuf>,
+
     // 缓存目录，默认为当前目录
     // 将把 python-x.x.x-embed-xxx.zip、get-pip.py 下载到此目录
     pub cache_dir: std::path::PathBuf,
@@ -23,37 +88,191 @@ pub struct Config {
     // 通过指定镜像站下载 pip 包，如果为空则不使用镜像站
     pub pip_mirror: String,
 
+    // 除 pip_mirror 之外额外使用的索引
+    pub extra_index_urls: Vec<String>,
+
+    // 信任的主机，允许使用 http 或无效的证书访问
+    pub trusted_hosts: Vec<String>,
+
+    // 原样传给 pip install 的额外参数，如 --prefer-binary
+    pub pip_args: Vec<String>,
+
+    // pip 配置文件，通过 PIP_CONFIG_FILE 环境变量传给 pip，uv 和内置安装器不读取此文件
+    pub pip_config: Option<std::path::PathBuf>,
+
+    // 在 cache_dir 中保留 pip 和 uv 的下载缓存，多次构建之间共享，默认每次重新下载
+    // 缓存不会进入运行时，可以用 clear_cache 清除
+    pub pip_cache: bool,
+
+    // 额外查找依赖包的位置，可以是本地目录（如 wheelhouse 下载的目录）或 URL
+    pub find_links: Vec<String>,
+
+    // 不访问任何索引，只从 find_links 中查找依赖包，用于离线构建
+    pub no_index: bool,
+
+    // 运行 pip download 等操作使用的本机 Python，为空时自动查找
+    pub host_python: String,
+
+    // 安装依赖包使用的后端，见 Backend
+    pub installer: Backend,
+
+    // uv 可执行文件路径，为空时从 PATH 中查找
+    pub uv: String,
+
     // 保留 Scripts 目录
     pub keep_scripts: bool,
 
     // 保留 dist-info 目录，删除此目录后将无法再通过 pip 管理依赖
     pub keep_dist_info: bool,
 
+    // 不保留完整的 dist-info 时，为每个包保留最小的元数据，供 importlib.metadata 使用
+    // 只保留 METADATA（去掉长描述）、entry_points.txt 和 top_level.txt
+    pub minimal_dist_info: bool,
+
+    // 始终保留完整 dist-info 的包
+    pub keep_dist_info_for: Vec<String>,
+
     // 保留 pip、setuptools、wheel 依赖包
     pub keep_pip: bool,
 
     // 优化编译级别：0（不优化），1（移除断言，关闭调试），2（同时删除文档字符串）
     pub optimize: u8,
 
+    // 按 optimize 重新编译标准库 pythonXY.zip，需要下载同版本的源码包
+    pub recompile_stdlib: bool,
+
+    // 并行编译的进程数，为 0 时按 CPU 数量
+    pub compile_jobs: usize,
+
+    // 按顺序匹配，第一个匹配的规则生效，用于需要读取自身源码的包（inspect.getsource、模板加载等）
+    pub source_rules: Vec<SourceRule>,
+
+    // 有文件编译失败时的处理方式，失败的文件保留源文件
+    pub compile_failure: CheckPolicy,
+
+    // .pyc 的失效检查方式
+    pub invalidation_mode: InvalidationMode,
+
+    // 可重现构建的时间戳（SOURCE_DATE_EPOCH），设置后运行时中文件的修改时间不晚于此时间
+    // zip 中的文件也使用此时间
+    pub source_date_epoch: Option<u64>,
+
+    // 删除的功能，如 sqlite、ssl、test_modules，删除对应的扩展模块、DLL 和标准库中的包
+    // 可用的功能见 features()
+    pub disabled_features: Vec<String>,
+
+    // 应用的入口脚本，设置后分析导入，删除 site-packages 和标准库中没有用到的模块
+    pub entry_script: Option<std::path::PathBuf>,
+
+    // 运行入口脚本记录实际导入的模块，否则静态分析
+    // 需要运行目标 python.exe，只能在 Windows 上使用 pip 或 uv 安装器时使用
+    pub trace_run: bool,
+
+    // 始终保留的模块及其子模块，用于插件、importlib.import_module 等动态导入
+    pub keep_modules: Vec<String>,
+
+    // 导入分析报告的保存路径，为空时保存到缓存目录中的 pyembed-shake-report.json
+    pub shake_report: Option<std::path::PathBuf>,
+
+    // 编译后删除测试、文档、类型存根、C 源文件和其他版本的 .pyc 等不需要的文件
+    pub strip: bool,
+
+    // 额外的删除规则文件，格式与 .gitignore 相同，可以用 [包名] 限定作用的包
+    pub strip_rules: Option<std::path::PathBuf>,
+
+    // 把纯 Python 包打包为 Lib/site-packages.zip，包含扩展模块等不能从 zip 导入的包保留在目录中
+    pub zip_site_packages: bool,
+
+    // 只把这些包打包为 zip，不为空时即使没有设置 zip_site_packages 也会打包
+    pub zip_packages: Vec<String>,
+
+    // 运行时的大小上限（字节），构建完成后超过时失败
+    pub max_size: Option<u64>,
+
     // 要安装的 pip 依赖包
+    // 可以是本地的 wheel、源码包、项目目录或 file:// URL，本地目录和源码包会先构建为 wheel
     pub packages: Vec<String>,
+
+    // 安装后检查已安装的包之间的依赖是否满足，与 pip check 相同
+    pub check: CheckPolicy,
+
+    // packages 中相对路径的起始目录，为空时使用当前目录
+    // 项目中声明的依赖使用 pyproject.toml 所在的目录
+    pub base_dir: std::path::PathBuf,
+
+    // 项目路径，可以是项目目录或 pyproject.toml 文件
+    // 将读取其中 [project].dependencies 并安装到运行时中
+    pub project: Option<std::path::PathBuf>,
+
+    // 同时安装项目 [project.optional-dependencies] 中的这些 extra
+    pub extras: Vec<String>,
+
+    // 将项目本身构建为 wheel 并安装（非 editable）
+    pub install_project: bool,
+
+    // 锁文件路径，默认为 <当前目录>\pyembed.lock
+    // 每次构建都会把实际安装的依赖包版本和哈希写入此文件
+    pub lock_file: std::path::PathBuf,
+
+    // 严格按锁文件安装，锁文件与当前配置不一致时报错
+    pub locked: bool,
+
+    // 导入 poetry.lock、uv.lock 或 Pipfile.lock，按目标环境安装其中锁定的版本
+    pub import_lock: Option<std::path::PathBuf>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             dir: std::env::current_dir().unwrap().join("pyembed_runtime"),
+            output_dir: None,
             cache_dir: std::env::current_dir().unwrap(),
             pyver: "latest".into(),
             is32: false,
             skip_download: false,
             python_mirror: "".into(),
             pip_mirror: "".into(),
+            extra_index_urls: vec![],
+            trusted_hosts: vec![],
+            pip_args: vec![],
+            pip_config: None,
+            pip_cache: false,
+            find_links: vec![],
+            no_index: false,
+            host_python: "".into(),
+            installer: Backend::Pip,
+            uv: "".into(),
             keep_scripts: false,
             keep_dist_info: false,
+            minimal_dist_info: false,
+            keep_dist_info_for: vec![],
             keep_pip: false,
             optimize: 0,
+            recompile_stdlib: false,
+            compile_jobs: 0,
+            source_rules: vec![],
+            compile_failure: CheckPolicy::Warn,
+            invalidation_mode: InvalidationMode::Timestamp,
+            source_date_epoch: None,
+            disabled_features: vec![],
+            entry_script: None,
+            trace_run: false,
+            keep_modules: vec![],
+            shake_report: None,
+            strip: false,
+            strip_rules: None,
+            zip_site_packages: false,
+            zip_packages: vec![],
+            max_size: None,
             packages: vec![],
+            check: CheckPolicy::Warn,
+            base_dir: std::path::PathBuf::new(),
+            project: None,
+            extras: vec![],
+            install_project: false,
+            lock_file: std::env::current_dir().unwrap().join("pyembed.lock"),
+            locked: false,
+            import_lock: None,
         }
     }
 }
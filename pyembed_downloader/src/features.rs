@@ -0,0 +1,184 @@
+// 按功能删除嵌入式包中不需要的扩展模块、DLL 和标准库中的包
+// 直接处理文件和 pythonXY.zip，不需要运行 Python
+
+use crate::config::Config;
+use crate::Result;
+
+struct Feature {
+    name: &'static str,
+    description: &'static str,
+    // 运行时目录中的文件，可以使用通配符
+    files: &'static [&'static str],
+    // pythonXY.zip 中的顶层包或模块
+    packages: &'static [&'static str],
+}
+
+const FEATURES: &[Feature] = &[
+    Feature {
+        name: "sqlite",
+        description: "sqlite3 模块",
+        files: &["_sqlite3.pyd", "sqlite3.dll"],
+        packages: &["sqlite3"],
+    },
+    Feature {
+        name: "ssl",
+        description: "ssl 模块和 OpenSSL，hashlib 会使用内置的实现",
+        files: &[
+            "_ssl.pyd",
+            "_hashlib.pyd",
+            "libssl-*.dll",
+            "libcrypto-*.dll",
+        ],
+        packages: &["ssl"],
+    },
+    Feature {
+        name: "decimal",
+        description: "decimal 的 C 实现，decimal 会使用纯 Python 的实现",
+        files: &["_decimal.pyd"],
+        packages: &[],
+    },
+    Feature {
+        name: "ctypes",
+        description: "ctypes 模块和 libffi",
+        files: &["_ctypes.pyd", "libffi-*.dll"],
+        packages: &["ctypes"],
+    },
+    Feature {
+        name: "msi",
+        description: "msilib 模块",
+        files: &["_msi.pyd"],
+        packages: &["msilib"],
+    },
+    Feature {
+        name: "compression",
+        description: "bz2、lzma 模块",
+        files: &["_bz2.pyd", "_lzma.pyd"],
+        packages: &["bz2", "lzma"],
+    },
+    Feature {
+        name: "test_modules",
+        description: "unittest、test 包和 _test* 扩展模块",
+        files: &["_test*.pyd"],
+        packages: &["unittest", "test"],
+    },
+    Feature {
+        name: "pydoc",
+        description: "pydoc 和 pydoc_data，help() 将不可用",
+        files: &[],
+        packages: &["pydoc", "pydoc_data"],
+    },
+    Feature {
+        name: "lib2to3",
+        description: "lib2to3 包",
+        files: &[],
+        packages: &["lib2to3"],
+    },
+    Feature {
+        name: "ensurepip",
+        description: "ensurepip 包",
+        files: &[],
+        packages: &["ensurepip"],
+    },
+    Feature {
+        name: "distutils",
+        description: "distutils 包",
+        files: &[],
+        packages: &["distutils"],
+    },
+];
+
+// 可以删除的功能和说明
+pub fn features() -> Vec<(&'static str, &'static str)> {
+    FEATURES.iter().map(|i| (i.name, i.description)).collect()
+}
+
+// 在构建开始时检查，避免下载和安装之后才发现功能名称错误
+pub(crate) fn check(config: &Config) -> Result<()> {
+    disabled(config)?;
+    Ok(())
+}
+
+fn disabled(config: &Config) -> Result<Vec<&'static Feature>> {
+    let mut disabled = vec![];
+    for name in config.disabled_features.iter() {
+        match FEATURES.iter().find(|i| i.name == name.trim()) {
+            Some(feature) => disabled.push(feature),
+            None => {
+                let names: Vec<&str> = FEATURES.iter().map(|i| i.name).collect();
+                return Err(
+                    format!("未知的功能：{}，可用的功能：{}", name, names.join("、")).into(),
+                );
+            }
+        }
+    }
+    Ok(disabled)
+}
+
+// 要在重新编译标准库之后调用
+pub(crate) fn remove(config: &Config, dir: &std::path::Path, pyver: &str) -> Result<()> {
+    let disabled = disabled(config)?;
+
+    let mut entries: Vec<std::fs::DirEntry> =
+        std::fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|i| i.file_name());
+    let zippath = dir.join(format!("python{}.zip", crate::utility::python_xy(pyver)));
+    for feature in disabled.iter() {
+        let mut bytes = 0;
+        let mut removed = vec![];
+        for pattern in feature.files {
+            let glob = globset::GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()?
+                .compile_matcher();
+            for entry in entries.iter() {
+                let path = entry.path();
+                if path.is_file() && glob.is_match(entry.file_name()) {
+                    bytes += entry.metadata()?.len();
+                    std::fs::remove_file(&path)?;
+                    removed.push(entry.file_name().to_string_lossy().to_string());
+                }
+            }
+        }
+        if !feature.packages.is_empty() && zippath.exists() {
+            let (members, size) = remove_packages(&zippath, feature.packages)?;
+            bytes += size;
+            if members > 0 {
+                removed.push(format!("{} 中的 {} 个文件", zippath.display(), members));
+            }
+        }
+        info!(
+            "删除功能 {}：{}",
+            feature.name,
+            crate::utility::format_size(bytes)
+        );
+        for i in removed {
+            info!("    {}", i);
+        }
+    }
+    Ok(())
+}
+
+// 重写 zip，不解压和重新压缩其他文件，返回删除的文件数量和压缩后的大小
+fn remove_packages(zippath: &std::path::Path, packages: &[&str]) -> Result<(usize, u64)> {
+    let mut removed = 0;
+    let mut bytes = 0;
+    let output = zippath.with_extension("zip.tmp");
+    {
+        let mut source = zip::ZipArchive::new(std::fs::File::open(zippath)?)?;
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&output)?);
+        for i in 0..source.len() {
+            let file = source.by_index_raw(i)?;
+            let top = file.name().split('/').next().unwrap_or_default();
+            let top = top.split('.').next().unwrap_or_default();
+            if packages.contains(&top) {
+                bytes += file.compressed_size();
+                removed += 1;
+            } else {
+                zip.raw_copy_file(file)?;
+            }
+        }
+        zip.finish()?;
+    }
+    std::fs::rename(&output, zippath)?;
+    Ok((removed, bytes))
+}
@@ -0,0 +1,336 @@
+// 安装依赖包的后端，pip 在运行时中执行，uv 在本机执行，目标运行时可以与本机不同
+// 内置安装器不通过此接口安装，见 native_install
+
+use crate::{new_command, new_python_command, process_output_to_log, Result};
+
+pub(crate) trait Installer {
+    // 安装依赖包，report 不为空时写入与 pip install --report 格式相同的安装报告
+    // constraints 为约束文件，安装的版本不能超出其中的约束
+    fn install(
+        &self,
+        packages: &[String],
+        constraints: Option<&std::path::Path>,
+        report: Option<&std::path::Path>,
+    ) -> Result<()>;
+
+    // 按 requirements 文件安装，要求哈希完全匹配，且不解析额外的依赖
+    fn install_locked(&self, requirements: &std::path::Path) -> Result<()>;
+
+    fn uninstall(&self, packages: &[&str]) -> Result<()>;
+
+    // 与 pip inspect 格式相同的已安装列表
+    fn inspect(&self) -> Result<serde_json::Value>;
+}
+
+// 索引参数和 pip_args 用于所有安装操作，pip 配置文件用于所有 pip 命令
+pub(crate) struct PipInstaller {
+    dir: std::path::PathBuf,
+    index_args: Vec<String>,
+    pip_args: Vec<String>,
+    cache_args: Vec<String>,
+    config_file: Option<std::path::PathBuf>,
+}
+
+impl PipInstaller {
+    pub fn new(
+        config: &crate::Config,
+        dir: &std::path::Path,
+        index_args: &[String],
+    ) -> Result<PipInstaller> {
+        Ok(PipInstaller {
+            dir: dir.to_path_buf(),
+            index_args: index_args.to_vec(),
+            pip_args: config.pip_args.clone(),
+            cache_args: crate::pip_cache_args(config)?,
+            config_file: crate::pip_config_file(config)?,
+        })
+    }
+
+    fn command(&self) -> std::process::Command {
+        let mut cmd = new_python_command(&self.dir);
+        if let Some(path) = &self.config_file {
+            cmd.env("PIP_CONFIG_FILE", path);
+        }
+        cmd
+    }
+
+    // 使用 get-pip.py 在运行时中安装 pip
+    pub fn setup(&self, get_pip: &std::path::Path) -> Result<()> {
+        let mut cmd = self.command();
+        cmd.arg(get_pip);
+        cmd.args(&self.cache_args);
+        cmd.arg("--no-warn-script-location");
+        cmd.args(&self.index_args);
+        cmd.args(&self.pip_args);
+        run(cmd, "安装 pip 失败")
+    }
+}
+
+impl Installer for PipInstaller {
+    fn install(
+        &self,
+        packages: &[String],
+        constraints: Option<&std::path::Path>,
+        report: Option<&std::path::Path>,
+    ) -> Result<()> {
+        let mut cmd = self.command();
+        cmd.args(["-m", "pip", "install", "--no-warn-script-location", "-U"]);
+        cmd.args(&self.cache_args);
+        cmd.args(&self.index_args);
+        cmd.args(&self.pip_args);
+        if let Some(constraints) = constraints {
+            cmd.arg("-c");
+            cmd.arg(constraints);
+        }
+        if let Some(report) = report {
+            cmd.arg("--report");
+            cmd.arg(report);
+        }
+        cmd.args(packages);
+        run(cmd, "安装依赖包失败")
+    }
+
+    fn install_locked(&self, requirements: &std::path::Path) -> Result<()> {
+        let mut cmd = self.command();
+        cmd.args([
+            "-m",
+            "pip",
+            "install",
+            "--no-warn-script-location",
+            "--require-hashes",
+            "--no-deps",
+            "-r",
+        ]);
+        cmd.arg(requirements);
+        cmd.args(&self.cache_args);
+        cmd.args(&self.index_args);
+        cmd.args(&self.pip_args);
+        run(cmd, "按锁文件安装依赖包失败")
+    }
+
+    fn uninstall(&self, packages: &[&str]) -> Result<()> {
+        let mut cmd = self.command();
+        cmd.args(["-m", "pip", "uninstall", "-y"]);
+        cmd.args(packages);
+        run(cmd, "卸载依赖包失败")
+    }
+
+    fn inspect(&self) -> Result<serde_json::Value> {
+        let mut cmd = self.command();
+        cmd.args(["-m", "pip", "inspect", "--local"]);
+        let output = cmd.output()?;
+        if !output.status.success() {
+            info!("{}", String::from_utf8_lossy(&output.stderr));
+            return Err(format!("pip inspect 失败 [{}]", output.status).into());
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+}
+
+// 本机与目标运行时不同时使用 --target 安装到 site-packages，否则直接使用运行时的 python.exe
+// uv 安装的包同样带有 RECORD，查询和卸载直接读取 dist-info，不需要运行 python.exe
+pub(crate) struct UvInstaller {
+    dir: std::path::PathBuf,
+    index_args: Vec<String>,
+    pip_args: Vec<String>,
+    cache_args: Vec<String>,
+    program: String,
+    // 目标 Python 的次版本，如 3.11
+    python: String,
+    is32: bool,
+    cross: bool,
+}
+
+impl UvInstaller {
+    pub fn new(
+        config: &crate::Config,
+        dir: &std::path::Path,
+        pyver: &str,
+        index_args: &[String],
+    ) -> Result<UvInstaller> {
+        let v: Vec<&str> = pyver.split('.').take(2).collect();
+        if config.pip_config.is_some() {
+            warn!("uv 不读取 pip 配置文件，忽略 pip_config");
+        }
+        // uv 中与 --trusted-host 对应的参数是 --allow-insecure-host
+        let index_args = index_args
+            .iter()
+            .map(|i| match i.as_str() {
+                "--trusted-host" => "--allow-insecure-host".to_string(),
+                _ => i.clone(),
+            })
+            .collect();
+        // uv 的缓存格式与 pip 不同，使用单独的目录
+        let cache_args = match crate::pip_cache_dir(config)? {
+            Some(dir) => vec![
+                "--cache-dir".to_string(),
+                dir.with_file_name("uv-cache").to_string_lossy().to_string(),
+            ],
+            None => vec!["--no-cache".to_string()],
+        };
+        Ok(UvInstaller {
+            dir: dir.to_path_buf(),
+            index_args,
+            pip_args: config.pip_args.clone(),
+            cache_args,
+            program: if config.uv.is_empty() {
+                "uv".to_string()
+            } else {
+                config.uv.clone()
+            },
+            python: v.join("."),
+            is32: config.is32,
+            cross: !cfg!(windows),
+        })
+    }
+
+    fn platform(&self) -> &str {
+        if self.is32 {
+            "i686-pc-windows-msvc"
+        } else {
+            "x86_64-pc-windows-msvc"
+        }
+    }
+
+    fn command(&self, subcommand: &str) -> std::process::Command {
+        let mut cmd = new_command(&self.program);
+        cmd.current_dir(&self.dir);
+        cmd.args(["pip", subcommand]);
+        if self.cross {
+            cmd.arg("--target");
+            cmd.arg(self.dir.join("Lib").join("site-packages"));
+            cmd.args(["--python-version", &self.python]);
+        } else {
+            cmd.arg("--python");
+            cmd.arg(self.dir.join("python.exe"));
+        }
+        cmd.args(["--python-platform", self.platform()]);
+        cmd
+    }
+}
+
+impl Installer for UvInstaller {
+    fn install(
+        &self,
+        packages: &[String],
+        constraints: Option<&std::path::Path>,
+        report: Option<&std::path::Path>,
+    ) -> Result<()> {
+        let report = match report {
+            Some(report) => report,
+            None => {
+                let mut cmd = self.command("install");
+                cmd.arg("--upgrade");
+                cmd.args(&self.cache_args);
+                cmd.args(&self.index_args);
+                cmd.args(&self.pip_args);
+                if let Some(constraints) = constraints {
+                    cmd.arg("-c");
+                    cmd.arg(constraints);
+                }
+                cmd.args(packages);
+                return run(cmd, "安装依赖包失败");
+            }
+        };
+
+        // uv 不支持 --report，先用 uv pip compile 按目标平台解析出带哈希的完整依赖列表，再按列表安装
+        let input = report.with_extension("in");
+        let compiled = report.with_extension("txt");
+        std::fs::write(&input, packages.join("\n"))?;
+        let mut cmd = new_command(&self.program);
+        cmd.current_dir(&self.dir);
+        cmd.args(["pip", "compile", "--generate-hashes"]);
+        cmd.args(&self.cache_args);
+        cmd.args(["--no-header", "--no-annotate", "--quiet"]);
+        cmd.args(["--python-version", &self.python]);
+        cmd.args(["--python-platform", self.platform()]);
+        if let Some(constraints) = constraints {
+            cmd.arg("-c");
+            cmd.arg(constraints);
+        }
+        cmd.arg("-o");
+        cmd.arg(&compiled);
+        cmd.arg(&input);
+        cmd.args(&self.index_args);
+        let result = run(cmd, "解析依赖失败").and_then(|_| self.install_locked(&compiled));
+        std::fs::remove_file(&input)?;
+        let content = if compiled.exists() {
+            let content = std::fs::read_to_string(&compiled)?;
+            std::fs::remove_file(&compiled)?;
+            content
+        } else {
+            String::new()
+        };
+        result?;
+        std::fs::write(report, serde_json::to_vec(&compiled_report(&content))?)?;
+        Ok(())
+    }
+
+    fn install_locked(&self, requirements: &std::path::Path) -> Result<()> {
+        let mut cmd = self.command("install");
+        cmd.args(["--require-hashes", "--no-deps", "-r"]);
+        cmd.arg(requirements);
+        cmd.args(&self.cache_args);
+        cmd.args(&self.index_args);
+        cmd.args(&self.pip_args);
+        run(cmd, "按锁文件安装依赖包失败")
+    }
+
+    fn uninstall(&self, packages: &[&str]) -> Result<()> {
+        for i in packages {
+            crate::wheel::uninstall(&self.dir, i)?;
+        }
+        Ok(())
+    }
+
+    fn inspect(&self) -> Result<serde_json::Value> {
+        inspect(&self.dir)
+    }
+}
+
+// 把 uv pip compile 生成的 requirements 转为 pip install --report 的格式
+// 同一版本可能有多个文件，hashes 中的 sha256 为数组
+fn compiled_report(content: &str) -> serde_json::Value {
+    let mut install: Vec<serde_json::Value> = vec![];
+    for line in content.lines() {
+        let line = line.trim().trim_end_matches('\\').trim();
+        if let Some(hash) = line.strip_prefix("--hash=sha256:") {
+            if let Some(last) = install.last_mut() {
+                last["download_info"]["archive_info"]["hashes"]["sha256"]
+                    .as_array_mut()
+                    .unwrap()
+                    .push(hash.into());
+            }
+        } else if let Some((name, version)) = line.split_once("==") {
+            install.push(serde_json::json!({
+                "metadata": { "name": name.trim(), "version": version.trim() },
+                "download_info": { "archive_info": { "hashes": { "sha256": [] } } },
+            }));
+        }
+    }
+    serde_json::json!({ "install": install })
+}
+
+// 直接读取 site-packages 中的 dist-info，生成与 pip inspect 格式相同的已安装列表
+pub(crate) fn inspect(dir: &std::path::Path) -> Result<serde_json::Value> {
+    let site = dir.join("Lib").join("site-packages");
+    let mut installed = vec![];
+    for (_, metadata) in crate::metadata::installed(&site)? {
+        installed.push(serde_json::json!({
+            "metadata": { "name": metadata.name, "version": metadata.version },
+        }));
+    }
+    Ok(serde_json::json!({ "installed": installed }))
+}
+
+fn run(mut cmd: std::process::Command, message: &str) -> Result<()> {
+    let mut process = cmd.spawn()?;
+    let (t1, t2) = process_output_to_log(&mut process);
+    let status = process.wait()?;
+    t1.join().unwrap();
+    t2.join().unwrap();
+    if !status.success() {
+        return Err(format!("{} [{}]", message, status).into());
+    }
+    Ok(())
+}
@@ -1,10 +1,34 @@
 #[macro_use]
 extern crate log;
 
+mod check;
+mod compile;
 mod config;
+mod features;
+mod installer;
+mod local;
+mod lock;
+mod lockfiles;
+mod markers;
+mod metadata;
+mod modify;
+mod pep440;
+mod pyproject;
+mod report;
+mod requirement;
+mod resolver;
+mod shake;
+mod size;
+mod stdlib;
+mod strip;
 mod utility;
+mod wheel;
+mod zipsite;
 
-pub use config::Config;
+pub use config::{Backend, CheckPolicy, Config, InvalidationMode, SourceMode, SourceRule};
+pub use features::features;
+pub use report::{table, BuildReport, CompileFailure, InstalledDistribution};
+pub use size::{size_table, DistributionSize, Size, SizeReport};
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -12,7 +36,10 @@ pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 // 此处不再检查 config，要确保传入正确的值
 // 进度回调为 (total, read)
 // 如果进度回调都为 -1，则表示重置进度，对于 cli，重置光标到行首，对于 gui，把滚动条设置为不确定值状态
-pub async fn run(config: &config::Config, progress_callback: &dyn Fn(i64, i64)) -> Result<()> {
+pub async fn run(
+    config: &config::Config,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<BuildReport> {
     let _job = utility::setup_job()?;
 
     let mut targetdir = config.dir.clone();
@@ -23,19 +50,556 @@ pub async fn run(config: &config::Config, progress_callback: &dyn Fn(i64, i64))
     if cachedir.is_relative() {
         cachedir = std::env::current_dir()?.join(cachedir);
     }
+    let mut lockfile = config.lock_file.clone();
+    if lockfile.is_relative() {
+        lockfile = std::env::current_dir()?.join(lockfile);
+    }
     std::fs::create_dir_all(&targetdir)?;
     std::fs::create_dir_all(&cachedir)?;
 
-    if config.skip_download {
+    if config.locked && config.import_lock.is_some() {
+        return Err("不能同时按锁文件安装和导入其他锁文件".into());
+    }
+    features::check(config)?;
+    if let Some(dir) = pip_cache_dir(config)? {
+        if dir.starts_with(&targetdir) {
+            return Err("pip 缓存目录不能位于运行时目录中，请修改 cache_dir".into());
+        }
+    }
+    let outdir = output_dir(config, &targetdir)?;
+    let locked = if config.locked {
+        warn!("读取锁文件 ...");
+        Some(lock::Lock::load(&lockfile)?)
+    } else {
+        None
+    };
+
+    let arch = if config.is32 { "x86" } else { "amd64" };
+    let (pyver, archive) = if config.skip_download {
         warn!("正在检查本地 Python 版本 ...");
-        let v = get_local_python_version(&targetdir)?;
+        let v = get_local_python_version(&targetdir, config.installer != Backend::Pip)?;
         info!("本地版本：{}.{}.{}", v.0, v.1, v.2);
+        (format!("{}.{}.{}", v.0, v.1, v.2), None)
     } else {
         if !is_empty_dir(&targetdir)? {
             return Err(format!("{} 目录非空", targetdir.display()).into());
         }
 
-        let v = if config.pyver == "latest" || config.pyver.is_empty() {
+        let v = get_python_version(config, &cachedir, locked.as_ref()).await?;
+        let (pyzippath, archive) =
+            download_python(config, &cachedir, &v, locked.as_ref(), progress_callback).await?;
+
+        warn!("解压文件 ...");
+        extract(&pyzippath, &targetdir)?;
+        (v, Some(archive))
+    };
+
+    warn!("修改 Python Path ...");
+    ensure_pth(&targetdir, &pyver)?;
+    if config.recompile_stdlib {
+        stdlib::download_source(config, &cachedir, &pyver, progress_callback).await?;
+    }
+
+    let index_args = pip_index_args(config)?;
+    let installer = new_installer(config, &targetdir, &pyver, &index_args)?;
+    let mut reports = vec![];
+    if config.installer == Backend::Pip {
+        let pippath = download_get_pip(&cachedir, config.no_index, progress_callback).await?;
+        warn!("安装 pip ...");
+        installer::PipInstaller::new(config, &targetdir, &index_args)?.setup(&pippath)?;
+        // 按锁文件安装时 pip、setuptools、wheel 的版本由锁文件固定，不在这里升级
+        // 否则记录安装报告，使它们和其他依赖一样写入锁文件，已是最新版本时报告为空，因此强制重新安装
+        if locked.is_none() {
+            let installer = installer.as_ref().unwrap();
+            let report = cachedir.join("pyembed-pip-report.json");
+            for packages in [
+                vec!["--force-reinstall".into(), "pip".into()],
+                vec![
+                    "--force-reinstall".into(),
+                    "setuptools".into(),
+                    "wheel".into(),
+                ],
+            ] {
+                installer.install(&packages, None, Some(&report))?;
+                reports.push(serde_json::from_slice(&std::fs::read(&report)?)?);
+                std::fs::remove_file(&report)?;
+            }
+        }
+    }
+
+    let imported = import_lock(config, &pyver)?;
+    // 运行时中有 pip 时在运行时中构建，否则使用本机 Python
+    let build = |source: &std::path::Path| {
+        let cmd = if config.installer == Backend::Pip && cfg!(windows) {
+            new_python_command(&targetdir)
+        } else {
+            new_host_python_command(config)
+        };
+        build_wheel(config, cmd, &cachedir, source, &index_args)
+    };
+    let (packages, specs, project) = collect_packages(config, imported.is_none(), &pyver, &build)?;
+    if let Some(lock) = &locked {
+        lock.check(&pyver, arch, archive.as_ref(), &specs)?;
+    }
+
+    if let Some(installer) = &installer {
+        for (name, pinned) in [
+            ("锁文件", locked.as_ref().map(|i| &i.packages)),
+            ("导入的锁文件", imported.as_ref()),
+        ] {
+            if let Some(pinned) = pinned {
+                warn!("按{}安装依赖包 ...", name);
+                let requirements = cachedir.join("pyembed-lock-requirements.txt");
+                std::fs::write(&requirements, lock::requirements(pinned)?)?;
+                let result = installer.install_locked(&requirements);
+                std::fs::remove_file(&requirements)?;
+                result?;
+            }
+        }
+        // 本地包不记录在锁文件中，依赖已经按锁文件安装，只安装本地包本身
+        let local = local_requirements(&packages);
+        if locked.is_some() && !local.is_empty() {
+            warn!("安装本地包 ...");
+            let mut args = vec!["--no-deps".to_string()];
+            args.extend(local);
+            installer.install(&args, None, None)?;
+        }
+        if locked.is_none() && !packages.is_empty() {
+            warn!("安装依赖包 ...");
+            let report = cachedir.join("pyembed-pip-report.json");
+            // 安装时会升级已安装的包，以导入的锁文件中的版本作为约束，避免改变已固定的版本
+            let constraints = cachedir.join("pyembed-lock-constraints.txt");
+            if let Some(pinned) = &imported {
+                std::fs::write(&constraints, lock::constraints(pinned))?;
+            }
+            let result = installer.install(
+                &packages,
+                imported.as_ref().map(|_| constraints.as_path()),
+                Some(&report),
+            );
+            if imported.is_some() {
+                std::fs::remove_file(&constraints)?;
+            }
+            result?;
+            reports.push(serde_json::from_slice(&std::fs::read(&report)?)?);
+            std::fs::remove_file(&report)?;
+        }
+    } else {
+        reports.push(
+            native_install(
+                config,
+                &targetdir,
+                &cachedir,
+                &pyver,
+                locked.as_ref().map(|i| i.packages.as_slice()),
+                imported.as_deref(),
+                &packages,
+                project.as_ref(),
+                progress_callback,
+            )
+            .await?,
+        );
+    }
+
+    if let (Some(project), true, Some(installer)) = (&project, config.install_project, &installer) {
+        warn!("安装项目 ...");
+        // pip 和 uv 都会先把项目构建为 wheel 再安装，不会使用 editable 模式
+        // 安装器的工作目录是运行时目录，必须传入绝对路径
+        let mut dir = project.dir.clone();
+        if dir.is_relative() {
+            dir = std::env::current_dir()?.join(dir);
+        }
+        let mut spec = dir.to_string_lossy().to_string();
+        if !config.extras.is_empty() {
+            spec = format!("{}[{}]", spec, config.extras.join(","));
+        }
+        if locked.is_some() || imported.is_some() {
+            // 依赖已经按锁文件安装，项目本身不能再引入其他依赖
+            installer.install(&["--no-deps".into(), spec], None, None)?;
+        } else {
+            installer.install(&[spec], None, None)?;
+        }
+    }
+
+    if locked.is_none() {
+        warn!("生成锁文件 ...");
+        // 本次没有重新下载的包，从旧的锁文件或导入的锁文件中取得哈希
+        let mut known = vec![];
+        if lockfile.exists() {
+            if let Ok(previous) = lock::Lock::load(&lockfile) {
+                known.extend(previous.packages);
+            }
+        }
+        known.extend(imported.iter().flatten().cloned());
+        let installed = match &installer {
+            Some(installer) => installer.inspect()?,
+            None => installer::inspect(&targetdir)?,
+        };
+        let mut lock = lock::Lock::new(&pyver, arch, archive, &specs, &installed, &reports, &known);
+        // pip 和 uv 从源码安装项目本身，没有哈希，不记录在锁文件中，按锁文件安装时以 --no-deps 重新安装
+        if let (Some(project), true, Some(_)) = (&project, config.install_project, &installer) {
+            let name = pyproject::normalize_name(&project.name);
+            lock.packages.retain(|i| i.name != name);
+        }
+        // 本地包每次从源码或本地 wheel 安装，构建出的 wheel 哈希不固定，同样不记录
+        let local: Vec<String> = local_requirements(&packages)
+            .iter()
+            .filter_map(|i| lock::requirement_name(i))
+            .collect();
+        lock.packages.retain(|i| !local.contains(&i.name));
+        lock.save(&lockfile)?;
+        info!("锁文件：{}", lockfile.display());
+    }
+
+    // 清理时可能删除 dist-info，需要事先读取安装结果
+    let mut requested: Vec<String> = packages
+        .iter()
+        .filter_map(|i| lock::requirement_name(i))
+        .collect();
+    if let (Some(project), true) = (&project, config.install_project) {
+        requested.push(pyproject::normalize_name(&project.name));
+    }
+    let pinned: Vec<lock::LockedPackage> = locked
+        .iter()
+        .flat_map(|i| i.packages.iter())
+        .chain(imported.iter().flatten())
+        .cloned()
+        .collect();
+    let mut installed = report::collect(
+        &targetdir.join("Lib").join("site-packages"),
+        &requested,
+        &reports,
+        &pinned,
+    )?;
+
+    let keeppip = keep_pip(config, &config.packages);
+    check_runtime(config, &targetdir, &pyver, keeppip)?;
+
+    let tracker = size::Tracker::new(&targetdir, &pyver)?;
+    let compile_failures = match &outdir {
+        Some(outdir) => {
+            export(&targetdir, outdir)?;
+            strip_runtime(config, outdir, &pyver, keeppip, None, progress_callback)?
+        }
+        None => strip_runtime(
+            config,
+            &targetdir,
+            &pyver,
+            keeppip,
+            installer.as_deref(),
+            progress_callback,
+        )?,
+    };
+    if !keeppip {
+        installed.retain(|i| !matches!(i.name.as_str(), "pip" | "setuptools" | "wheel"));
+    }
+    let mut size = tracker.finish(outdir.as_deref().unwrap_or(&targetdir), &pyver)?;
+    if !keeppip {
+        size.distributions.retain(|i| {
+            !matches!(
+                pyproject::normalize_name(&i.name).as_str(),
+                "pip" | "setuptools" | "wheel"
+            )
+        });
+    }
+    check_max_size(config, &size)?;
+
+    warn!("完成！");
+    Ok(BuildReport {
+        installed,
+        compile_failures,
+        size,
+    })
+}
+
+// 超过 max_size 时输出各个包的大小并报错
+fn check_max_size(config: &config::Config, size: &size::SizeReport) -> Result<()> {
+    if let Some(max_size) = config.max_size {
+        if size.total.after > max_size {
+            for line in size_table(size) {
+                info!("{}", line);
+            }
+            return Err(format!(
+                "运行时大小 {} 超过上限 {}",
+                utility::format_size(size.total.after),
+                utility::format_size(max_size)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+// 为 config 预先下载所需的全部 wheel 到 dir 目录
+// 使用本机的 Python 运行 pip download，按目标平台和 Python 版本下载，因此可以在任意系统上运行
+// 同时把 Python 安装包和 get-pip.py 下载到缓存目录，之后配合 find_links 和 no_index 即可离线构建
+pub async fn wheelhouse(
+    config: &config::Config,
+    dir: &std::path::Path,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<()> {
+    let mut dir = dir.to_path_buf();
+    if dir.is_relative() {
+        dir = std::env::current_dir()?.join(dir);
+    }
+    let mut cachedir = config.cache_dir.clone();
+    if cachedir.is_relative() {
+        cachedir = std::env::current_dir()?.join(cachedir);
+    }
+    std::fs::create_dir_all(&dir)?;
+    std::fs::create_dir_all(&cachedir)?;
+
+    let locked = if config.locked {
+        warn!("读取锁文件 ...");
+        let mut lockfile = config.lock_file.clone();
+        if lockfile.is_relative() {
+            lockfile = std::env::current_dir()?.join(lockfile);
+        }
+        Some(lock::Lock::load(&lockfile)?)
+    } else {
+        None
+    };
+
+    let pyver = get_python_version(config, &cachedir, locked.as_ref()).await?;
+    download_python(
+        config,
+        &cachedir,
+        &pyver,
+        locked.as_ref(),
+        progress_callback,
+    )
+    .await?;
+    download_get_pip(&cachedir, false, progress_callback).await?;
+    if config.recompile_stdlib {
+        stdlib::download_source(config, &cachedir, &pyver, progress_callback).await?;
+    }
+
+    // 下载时需要访问索引，忽略 no_index
+    let index_args: Vec<String> = pip_index_args(config)?
+        .into_iter()
+        .filter(|i| i != "--no-index")
+        .collect();
+
+    warn!("下载 pip ...");
+    pip_download(
+        config,
+        &dir,
+        &pyver,
+        ["pip", "setuptools", "wheel"],
+        &index_args,
+    )?;
+
+    let imported = import_lock(config, &pyver)?;
+    let build = |source: &std::path::Path| {
+        build_wheel(
+            config,
+            new_host_python_command(config),
+            &cachedir,
+            source,
+            &index_args,
+        )
+    };
+    let (mut packages, _, project) = collect_packages(config, imported.is_none(), &pyver, &build)?;
+    // 离线构建项目本身时还需要 [build-system].requires 中的构建依赖
+    if let (Some(project), true) = (&project, config.install_project) {
+        for i in project.build_requires.iter() {
+            if !packages.contains(i) {
+                packages.push(i.clone());
+            }
+        }
+    }
+
+    for (name, pinned) in [
+        ("锁文件", locked.map(|i| i.packages)),
+        ("导入的锁文件", imported),
+    ] {
+        if let Some(pinned) = pinned {
+            warn!("按{}下载依赖包 ...", name);
+            let requirements = cachedir.join("pyembed-lock-requirements.txt");
+            std::fs::write(&requirements, lock::requirements(&pinned)?)?;
+            let result = pip_download(
+                config,
+                &dir,
+                &pyver,
+                [
+                    "--require-hashes",
+                    "--no-deps",
+                    "-r",
+                    &requirements.to_string_lossy(),
+                ],
+                &index_args,
+            );
+            std::fs::remove_file(&requirements)?;
+            result?;
+        }
+    }
+
+    if !packages.is_empty() {
+        warn!("下载依赖包 ...");
+        pip_download(config, &dir, &pyver, &packages, &index_args)?;
+    }
+
+    warn!("完成！");
+    Ok(())
+}
+
+// 不运行 pip，直接从 find_links 和索引中解析依赖，输出要下载的 wheel 列表
+// output 不为空时把结果以 JSON 格式写入该文件
+pub async fn resolve(
+    config: &config::Config,
+    output: Option<&std::path::Path>,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<()> {
+    let mut cachedir = config.cache_dir.clone();
+    if cachedir.is_relative() {
+        cachedir = std::env::current_dir()?.join(cachedir);
+    }
+    let locked = if config.locked {
+        warn!("读取锁文件 ...");
+        let mut lockfile = config.lock_file.clone();
+        if lockfile.is_relative() {
+            lockfile = std::env::current_dir()?.join(lockfile);
+        }
+        Some(lock::Lock::load(&lockfile)?)
+    } else {
+        None
+    };
+    let pyver = get_python_version(config, &cachedir, locked.as_ref()).await?;
+    let env = markers::Environment::new(&pyver, config.is32);
+    let mut finder = new_finder(config, &pyver, &cachedir)?;
+
+    let imported = import_lock(config, &pyver)?;
+    let index_args = pip_index_args(config)?;
+    let build = |source: &std::path::Path| {
+        build_wheel(
+            config,
+            new_host_python_command(config),
+            &cachedir,
+            source,
+            &index_args,
+        )
+    };
+    let (mut packages, _, project) = collect_packages(config, imported.is_none(), &pyver, &build)?;
+    if let (Some(project), true) = (&project, config.install_project) {
+        packages.push(project.name.clone());
+    }
+
+    let mut plan = vec![];
+    let mut skip = vec![];
+    if let Some(pinned) = locked.as_ref().map(|i| &i.packages).or(imported.as_ref()) {
+        for i in pinned {
+            plan.push(finder.pinned(i).await?);
+            skip.push(i.name.clone());
+        }
+    }
+    if locked.is_none() && !packages.is_empty() {
+        warn!("解析依赖 ...");
+        for (candidate, _) in
+            resolver::resolve(&mut finder, &packages, &env, &skip, progress_callback).await?
+        {
+            plan.push(candidate);
+        }
+    }
+
+    info!("解析结果");
+    let mut result = vec![];
+    for i in plan.iter() {
+        // 本地文件和没有提供哈希的索引需要读取文件计算
+        let sha256 = match &i.sha256 {
+            Some(hash) => hash.clone(),
+            None => utility::sha256_hex(&std::fs::read(finder.fetch(i, progress_callback).await?)?),
+        };
+        info!("{}=={}", i.name, i.version);
+        info!("    {}", i.url);
+        info!("    sha256:{}", sha256);
+        result.push(serde_json::json!({
+            "name": i.name,
+            "version": i.version.to_string(),
+            "filename": i.filename,
+            "url": i.url,
+            "sha256": sha256,
+        }));
+    }
+    if let Some(output) = output {
+        std::fs::write(output, serde_json::to_string_pretty(&result)?)?;
+        info!("已保存到：{}", output.display());
+    }
+
+    warn!("完成！");
+    Ok(())
+}
+
+// 清除缓存目录中 pip、uv 的下载缓存，以及内置安装器下载的 wheel
+// Python 安装包、get-pip.py 和构建出的本地 wheel 不受影响
+pub fn clear_cache(config: &config::Config) -> Result<()> {
+    let cachedir = std::env::current_dir()?.join(&config.cache_dir);
+    for name in ["pip-cache", "uv-cache", "wheels"] {
+        let dir = cachedir.join(name);
+        if dir.exists() {
+            info!("删除目录：{}", dir.display());
+            std::fs::remove_dir_all(&dir)?;
+        }
+    }
+    warn!("完成！");
+    Ok(())
+}
+
+// 在已经构建好的运行时中添加依赖包，已安装的包会跳过
+pub async fn add(
+    config: &config::Config,
+    packages: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<()> {
+    modify::modify(config, modify::Operation::Add, packages, progress_callback).await
+}
+
+// 从已经构建好的运行时中删除依赖包，不会删除它们的依赖
+pub async fn remove(
+    config: &config::Config,
+    packages: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<()> {
+    modify::modify(
+        config,
+        modify::Operation::Remove,
+        packages,
+        progress_callback,
+    )
+    .await
+}
+
+// 把已经构建好的运行时中的依赖包升级到最新版本，依赖只在需要时升级
+pub async fn upgrade(
+    config: &config::Config,
+    packages: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<()> {
+    modify::modify(
+        config,
+        modify::Operation::Upgrade,
+        packages,
+        progress_callback,
+    )
+    .await
+}
+
+// 确定要使用的 Python 版本，按锁文件安装且没有指定版本时使用锁文件中的版本
+async fn get_python_version(
+    config: &config::Config,
+    cachedir: &std::path::Path,
+    locked: Option<&lock::Lock>,
+) -> Result<String> {
+    let v =
+        if let (Some(lock), true) = (locked, config.pyver == "latest" || config.pyver.is_empty()) {
+            info!("锁定版本：{}", lock.python);
+            lock.python.clone()
+        } else if (config.pyver == "latest" || config.pyver.is_empty()) && config.no_index {
+            // 离线时无法获取最新版本号，使用缓存中最新的嵌入式安装包
+            let v = latest_cached_python_version(config, cachedir)?
+                .ok_or("离线模式下无法获取最新版本号，缓存中也没有嵌入式安装包，请指定版本号")?;
+            info!("离线模式，使用缓存中的最新版本：{}", v);
+            v
+        } else if config.pyver == "latest" || config.pyver.is_empty() {
             warn!("正在获取最新版本号 ...");
             let v = get_latest_python_version().await?;
             info!("最新版本：{}", v);
@@ -48,113 +612,649 @@ pub async fn run(config: &config::Config, progress_callback: &dyn Fn(i64, i64))
             info!("指定版本：{}", v);
             v
         };
+    // 锁文件和网页中取得的版本号也要检查，之后都按 x.y.z 处理
+    if utility::regex_find(r"^\d+\.\d+\.\d+$", &v).is_none() {
+        return Err(format!("版本号格式错误：{}", v).into());
+    }
+    Ok(v)
+}
 
-        warn!("正在获取下载信息 ...");
-        let python_mirror = if config.python_mirror.is_empty() {
-            None
-        } else {
-            Some(config.python_mirror.as_str())
+// 缓存目录中最新的嵌入式安装包的版本号
+fn latest_cached_python_version(
+    config: &config::Config,
+    cachedir: &std::path::Path,
+) -> Result<Option<String>> {
+    let arch = if config.is32 { "x86" } else { "amd64" };
+    let mut latest: Option<pep440::Version> = None;
+    for entry in std::fs::read_dir(cachedir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        let v = match name
+            .strip_prefix("python-")
+            .and_then(|i| i.strip_suffix(&format!("-embed-{}.zip", arch)))
+        {
+            Some(v) if utility::regex_find(r"^\d+\.\d+\.\d+$", v).is_some() => v,
+            _ => continue,
+        };
+        let v: pep440::Version = v.parse()?;
+        match &latest {
+            Some(i) if *i >= v => {}
+            _ => latest = Some(v),
+        }
+    }
+    Ok(latest.map(|i| i.to_string()))
+}
+
+// 下载嵌入式安装包到缓存目录，缓存中已有且哈希一致时跳过下载
+async fn download_python(
+    config: &config::Config,
+    cachedir: &std::path::Path,
+    ver: &str,
+    locked: Option<&lock::Lock>,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<(std::path::PathBuf, lock::Archive)> {
+    let arch = if config.is32 { "x86" } else { "amd64" };
+    let filename = format!("python-{}-embed-{}.zip", ver, arch);
+    let pyzippath = cachedir.join(&filename);
+
+    // 离线模式下无法获取官方哈希，直接使用缓存中的文件，有锁文件时按锁文件记录的哈希校验
+    if config.no_index && pyzippath.exists() {
+        info!("离线模式，使用缓存中的 {}", filename);
+        let data = std::fs::read(&pyzippath)?;
+        let archive = lock::Archive {
+            name: filename,
+            md5: format!("{:x}", md5::compute(&data)),
+            sha256: utility::sha256_hex(&data),
         };
-        let info = get_python_download_info(&v, config.is32, python_mirror).await?;
-        info!("下载链接：{}", info.0);
-        info!("文件哈希：{}", info.1);
-
-        let arch = if config.is32 { "x86" } else { "amd64" };
-        let filename = format!("python-{}-embed-{}.zip", v, arch);
-        let pyzippath = cachedir.join(filename);
-
-        let mut pyzipexists = false;
-        if pyzippath.exists() {
-            if let Ok(pyembeddata) = std::fs::read(&pyzippath) {
-                let hash = format!("{:x}", md5::compute(&pyembeddata));
-                if hash.eq_ignore_ascii_case(&info.1) {
-                    info!("文件已存在，跳过下载");
-                    pyzipexists = true;
+        match locked.and_then(|i| i.archive.as_ref()) {
+            Some(expected) if expected.name == archive.name => {
+                if !expected.sha256.eq_ignore_ascii_case(&archive.sha256) {
+                    info!("预期：{}", expected.sha256);
+                    info!("实际：{}", archive.sha256);
+                    return Err(format!("{} 与锁文件记录的哈希不一致", archive.name).into());
                 }
+                info!("已按锁文件校验文件完整性");
             }
+            _ => warn!(
+                "离线模式且锁文件中没有 {} 的哈希，无法校验文件完整性",
+                archive.name
+            ),
         }
+        return Ok((pyzippath, archive));
+    }
+
+    warn!("正在获取下载信息 ...");
+    let python_mirror = if config.python_mirror.is_empty() {
+        None
+    } else {
+        Some(config.python_mirror.as_str())
+    };
+    let info = get_python_download_info(ver, config.is32, python_mirror).await?;
+    info!("下载链接：{}", info.0);
+    info!("文件哈希：{}", info.1);
 
-        if !pyzipexists {
-            warn!("正在下载 ...");
-            let pyembeddata = download_progress(&info.0, progress_callback).await?;
-            progress_callback(-1, -1);
-            warn!("校验文件完整性 ...");
+    let mut pyzipexists = false;
+    if pyzippath.exists() {
+        if let Ok(pyembeddata) = std::fs::read(&pyzippath) {
             let hash = format!("{:x}", md5::compute(&pyembeddata));
-            if !hash.eq_ignore_ascii_case(&info.1) {
-                info!("文件哈希不匹配");
-                info!("预期：{}", info.1);
-                info!("实际：{}", hash);
-                return Err("文件哈希不匹配".into());
+            if hash.eq_ignore_ascii_case(&info.1) {
+                info!("文件已存在，跳过下载");
+                pyzipexists = true;
             }
+        }
+    }
 
-            std::fs::write(&pyzippath, &pyembeddata)?;
+    if !pyzipexists {
+        warn!("正在下载 ...");
+        let pyembeddata = download_progress(&info.0, progress_callback).await?;
+        progress_callback(-1, -1);
+        warn!("校验文件完整性 ...");
+        let hash = format!("{:x}", md5::compute(&pyembeddata));
+        if !hash.eq_ignore_ascii_case(&info.1) {
+            info!("文件哈希不匹配");
+            info!("预期：{}", info.1);
+            info!("实际：{}", hash);
+            return Err("文件哈希不匹配".into());
         }
 
-        warn!("解压文件 ...");
-        extract(&pyzippath, &targetdir)?;
+        std::fs::write(&pyzippath, &pyembeddata)?;
     }
 
+    let archive = lock::Archive {
+        name: filename,
+        md5: info.1.to_lowercase(),
+        sha256: utility::sha256_hex(&std::fs::read(&pyzippath)?),
+    };
+    Ok((pyzippath, archive))
+}
+
+async fn download_get_pip(
+    cachedir: &std::path::Path,
+    offline: bool,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<std::path::PathBuf> {
     let pippath = cachedir.join("get-pip.py");
-    if !pippath.exists() {
-        warn!("正在下载 pip ...");
-        let pipdata = {
-            match download_progress("https://bootstrap.pypa.io/get-pip.py", progress_callback).await
-            {
-                Ok(data) => data,
-                Err(err) => {
-                    info!("下载 pip 失败：{}", err);
-                    warn!("正在从备用地址下载 pip ...");
-                    download_progress(
-                        "https://github.com/pypa/get-pip/raw/main/public/get-pip.py",
-                        progress_callback,
-                    )
-                    .await?
-                }
+    if pippath.exists() {
+        return Ok(pippath);
+    }
+    if offline {
+        return Err(format!(
+            "离线模式下 {} 中没有 get-pip.py，请先运行 wheelhouse 下载",
+            cachedir.display()
+        )
+        .into());
+    }
+    warn!("正在下载 pip ...");
+    let pipdata = {
+        match download_progress("https://bootstrap.pypa.io/get-pip.py", progress_callback).await {
+            Ok(data) => data,
+            Err(err) => {
+                info!("下载 pip 失败：{}", err);
+                warn!("正在从备用地址下载 pip ...");
+                download_progress(
+                    "https://github.com/pypa/get-pip/raw/main/public/get-pip.py",
+                    progress_callback,
+                )
+                .await?
             }
+        }
+    };
+    progress_callback(-1, -1);
+    std::fs::write(&pippath, &pipdata)?;
+    Ok(pippath)
+}
+
+fn import_lock(config: &config::Config, pyver: &str) -> Result<Option<Vec<lock::LockedPackage>>> {
+    let path = match &config.import_lock {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    warn!("导入锁文件 ...");
+    let env = markers::Environment::new(pyver, config.is32);
+    let imported = lockfiles::import(path, &env, &config.extras)?;
+    for i in imported.iter() {
+        info!("{}=={}", i.name, i.version);
+    }
+    Ok(Some(imported))
+}
+
+// 合并 config.packages 和项目中声明的依赖
+// 导入的锁文件中已经包含了项目的依赖，此时 with_project_deps 为 false
+// 本地包转为 name @ file:///... 形式，本地目录和源码包由 build 构建为 wheel
+// 返回要安装的依赖声明和原始的依赖声明，前者中的本地包已转为 name @ file:///... 形式
+// 后者用于锁文件，不含构建目录、缓存目录等与机器相关的绝对路径
+fn collect_packages(
+    config: &config::Config,
+    with_project_deps: bool,
+    pyver: &str,
+    build: &dyn Fn(&std::path::Path) -> Result<std::path::PathBuf>,
+) -> Result<(Vec<String>, Vec<String>, Option<pyproject::Project>)> {
+    let localize = |spec: &String, base: &std::path::Path| -> Result<String> {
+        let package = match local::LocalPackage::parse(spec, base)? {
+            Some(package) => package,
+            None => return Ok(spec.clone()),
         };
-        progress_callback(-1, -1);
-        std::fs::write(&pippath, &pipdata)?;
+        let wheel = if package.is_wheel() {
+            package.path.clone()
+        } else {
+            warn!("构建 {} ...", package.path.display());
+            build(&package.path)?
+        };
+        package.requirement(&wheel, pyver, config.is32)
+    };
+
+    let base = std::env::current_dir()?.join(&config.base_dir);
+    let mut packages = vec![];
+    let mut specs = vec![];
+    for i in config.packages.iter() {
+        packages.push(localize(i, &base)?);
+        specs.push(i.clone());
+    }
+    let project = match &config.project {
+        Some(path) if !with_project_deps => Some(pyproject::Project::load(path)?),
+        Some(path) => {
+            warn!("读取项目依赖 ...");
+            let project = pyproject::Project::load(path)?;
+            if project.is_dynamic() && !config.install_project {
+                return Err("项目的 dependencies 为 dynamic，需要同时安装项目本身".into());
+            }
+            for i in project.dependencies(&config.extras)? {
+                info!("{}", i);
+                let package = localize(&i, &project.dir)?;
+                if !packages.contains(&package) {
+                    packages.push(package);
+                    specs.push(i);
+                }
+            }
+            Some(project)
+        }
+        None => None,
+    };
+    Ok((packages, specs, project))
+}
+
+// pip 的索引相关参数，运行 pip 时的工作目录是运行时目录，本地路径需要转为绝对路径
+fn pip_index_args(config: &config::Config) -> Result<Vec<String>> {
+    let mut args = vec![];
+    if !config.pip_mirror.is_empty() {
+        args.push("-i".to_string());
+        args.push(config.pip_mirror.clone());
+    }
+    for i in config.extra_index_urls.iter() {
+        args.push("--extra-index-url".to_string());
+        args.push(i.clone());
+    }
+    for i in config.trusted_hosts.iter() {
+        args.push("--trusted-host".to_string());
+        args.push(i.clone());
+    }
+    for i in config.find_links.iter() {
+        args.push("--find-links".to_string());
+        if i.contains("://") {
+            args.push(i.clone());
+        } else {
+            let mut p = std::path::PathBuf::from(i);
+            if p.is_relative() {
+                p = std::env::current_dir()?.join(p);
+            }
+            args.push(p.to_string_lossy().to_string());
+        }
+    }
+    if config.no_index {
+        args.push("--no-index".to_string());
+    }
+    Ok(args)
+}
+
+// 输出目录的绝对路径，不能与工作目录互相包含
+fn output_dir(
+    config: &config::Config,
+    targetdir: &std::path::Path,
+) -> Result<Option<std::path::PathBuf>> {
+    let outdir = match &config.output_dir {
+        Some(outdir) => std::env::current_dir()?.join(outdir),
+        None => return Ok(None),
+    };
+    if outdir.starts_with(targetdir) || targetdir.starts_with(&outdir) {
+        return Err("输出目录不能与工作目录相同或互相包含".into());
     }
+    Ok(Some(outdir))
+}
 
-    warn!("修改 Python Path ...");
-    ensure_pth(&targetdir)?;
+// 依赖中明确要求了 pip、setuptools 或 wheel 时，清理时保留它们
+fn keep_pip(config: &config::Config, packages: &[String]) -> bool {
+    config.keep_pip
+        || packages
+            .iter()
+            .any(|i| i == "pip" || i == "setuptools" || i == "wheel")
+}
 
-    warn!("安装 pip ...");
-    let pip_mirror = if config.pip_mirror.is_empty() {
-        None
+// 把构建目录复制到输出目录，输出目录中原有的运行时会被删除
+fn export(targetdir: &std::path::Path, outdir: &std::path::Path) -> Result<()> {
+    warn!("导出到 {} ...", outdir.display());
+    if outdir.exists() {
+        // 防止误删其他文件，只覆盖空目录或之前导出的运行时
+        if !is_empty_dir(outdir)? && !outdir.join("python.exe").exists() {
+            return Err(format!("{} 不是空目录，也不是运行时", outdir.display()).into());
+        }
+        std::fs::remove_dir_all(outdir)?;
+    }
+    utility::copy_dir(targetdir, outdir)
+}
+
+// 编译并删除不需要的文件，installer 为 None 时按 RECORD 卸载 pip 等
+fn strip_runtime(
+    config: &config::Config,
+    dir: &std::path::Path,
+    pyver: &str,
+    keeppip: bool,
+    installer: Option<&dyn installer::Installer>,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<Vec<CompileFailure>> {
+    normalize_mtime(config, dir)?;
+    let mut shaker = match config.entry_script {
+        Some(_) => {
+            warn!("正在分析导入 ...");
+            let mut shaker = shake::trace(config, dir, pyver)?;
+            shaker.shake_site_packages(dir)?;
+            Some(shaker)
+        }
+        None => None,
+    };
+    warn!("正在编译 ...");
+    let failures = compile_runtime(config, dir, pyver, &[], progress_callback)?;
+    if config.strip || config.strip_rules.is_some() {
+        warn!("正在删除不需要的文件 ...");
+        strip::strip(config, dir, pyver, &[])?;
+    }
+    if config.zip_site_packages || !config.zip_packages.is_empty() {
+        warn!("正在打包 site-packages ...");
+        zipsite::pack(config, dir, pyver)?;
+    }
+    if config.recompile_stdlib {
+        warn!("正在重新编译标准库 ...");
+        stdlib::recompile(config, dir, pyver)?;
+    }
+    if !config.disabled_features.is_empty() {
+        warn!("正在删除不需要的功能 ...");
+        features::remove(config, dir, pyver)?;
+    }
+    if let Some(shaker) = &mut shaker {
+        shaker.shake_stdlib(dir, pyver)?;
+        shaker.save(config)?;
+    }
+    warn!("正在清理 ...");
+    cleanup(config, dir, keeppip, installer)?;
+    normalize_mtime(config, dir)?;
+    Ok(failures)
+}
+
+// 设置了 source_date_epoch 时统一文件的修改时间
+// 编译前调用使按时间戳失效检查的 .pyc 中记录的时间相同
+fn normalize_mtime(config: &config::Config, dir: &std::path::Path) -> Result<()> {
+    if let Some(epoch) = config.source_date_epoch {
+        info!("统一文件修改时间：SOURCE_DATE_EPOCH={}", epoch);
+        utility::clamp_mtime(dir, epoch)?;
+    }
+    Ok(())
+}
+
+// 按 config.check 检查已安装的包之间的依赖，清理时会删除的 pip 等按未安装处理
+fn check_runtime(
+    config: &config::Config,
+    dir: &std::path::Path,
+    pyver: &str,
+    keeppip: bool,
+) -> Result<()> {
+    if config.check == CheckPolicy::Ignore {
+        return Ok(());
+    }
+    warn!("检查依赖 ...");
+    let removed: &[&str] = if keeppip {
+        &[]
     } else {
-        Some(config.pip_mirror.as_str())
+        &["pip", "setuptools", "wheel"]
+    };
+    let problems = check::check(
+        &dir.join("Lib").join("site-packages"),
+        &markers::Environment::new(pyver, config.is32),
+        removed,
+    )?;
+    for i in problems.iter() {
+        error!("{}", i);
+    }
+    if !problems.is_empty() && config.check == CheckPolicy::Fail {
+        return Err(format!("发现 {} 个依赖问题", problems.len()).into());
+    }
+    Ok(())
+}
+
+// 内置安装器不通过 Installer 接口安装，返回 None
+fn new_installer(
+    config: &config::Config,
+    dir: &std::path::Path,
+    pyver: &str,
+    index_args: &[String],
+) -> Result<Option<Box<dyn installer::Installer>>> {
+    Ok(match config.installer {
+        Backend::Pip => Some(Box::new(installer::PipInstaller::new(
+            config, dir, index_args,
+        )?)),
+        Backend::Uv => Some(Box::new(installer::UvInstaller::new(
+            config, dir, pyver, index_args,
+        )?)),
+        Backend::Native => None,
+    })
+}
+
+// 使用内置安装器安装 wheel，从 find_links 中的本地目录和索引中查找
+// 锁文件和导入的锁文件中的包按版本和哈希安装，不再解析它们的依赖
+// 按锁文件安装时只安装锁定的包，packages 只用于检查锁文件是否过期
+// 返回与 pip install --report 格式相同的安装报告，用于生成锁文件
+#[allow(clippy::too_many_arguments)]
+async fn native_install(
+    config: &config::Config,
+    dir: &std::path::Path,
+    cachedir: &std::path::Path,
+    pyver: &str,
+    locked: Option<&[lock::LockedPackage]>,
+    imported: Option<&[lock::LockedPackage]>,
+    packages: &[String],
+    project: Option<&pyproject::Project>,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<serde_json::Value> {
+    let mut finder = new_finder(config, pyver, cachedir)?;
+    let env = markers::Environment::new(pyver, config.is32);
+
+    let mut report = vec![];
+    let mut install = |path: &std::path::Path, url: &str, requested: bool| -> Result<()> {
+        let metadata = wheel::install(path, dir, requested)?;
+        report.push(serde_json::json!({
+            "metadata": { "name": metadata.name, "version": metadata.version },
+            "download_info": {
+                "url": url,
+                "archive_info": { "hashes": { "sha256": utility::sha256_hex(&std::fs::read(path)?) } },
+            },
+        }));
+        Ok(())
     };
-    setup_pip(&targetdir, &pippath, pip_mirror)?;
-    pip_install(&targetdir, &["pip"], pip_mirror)?;
-    pip_install(&targetdir, &["setuptools", "wheel"], pip_mirror)?;
 
-    if config.packages.len() > 0 {
+    let mut skip = vec![];
+    if let Some(pinned) = locked.or(imported) {
+        warn!("按锁文件安装依赖包 ...");
+        for i in pinned {
+            let candidate = finder.pinned(i).await?;
+            let path = finder.fetch(&candidate, progress_callback).await?;
+            install(&path, &candidate.url, false)?;
+            skip.push(i.name.clone());
+        }
+    }
+
+    // 本地包已经转为 name @ file:///... 形式，由解析器直接使用
+    let mut requirements = vec![];
+    if locked.is_none() {
+        requirements.extend(packages.iter().cloned());
+    } else {
+        // 本地包不记录在锁文件中，只安装本地包本身
+        for i in local_requirements(packages) {
+            let req: requirement::Requirement = i.parse()?;
+            let candidate = finder.direct(req.url.as_deref().unwrap_or_default())?;
+            let path = finder.fetch(&candidate, progress_callback).await?;
+            install(&path, &candidate.url, true)?;
+        }
+    }
+    // 项目本身需要事先构建为 wheel 放在 find_links 中
+    if let (Some(project), true, None) = (project, config.install_project, locked) {
+        if config.extras.is_empty() {
+            requirements.push(project.name.clone());
+        } else {
+            requirements.push(format!("{}[{}]", project.name, config.extras.join(",")));
+        }
+    }
+    if !requirements.is_empty() {
+        warn!("解析依赖 ...");
+        let plan =
+            resolver::resolve(&mut finder, &requirements, &env, &skip, progress_callback).await?;
         warn!("安装依赖包 ...");
-        pip_install(&targetdir, &config.packages, pip_mirror)?;
+        for (candidate, requested) in plan {
+            let path = finder.fetch(&candidate, progress_callback).await?;
+            install(&path, &candidate.url, requested)?;
+        }
     }
+    Ok(serde_json::json!({ "install": report }))
+}
 
-    warn!("正在编译 ...");
-    compile(&targetdir, config.optimize)?;
+// 已转为 name @ file:///... 形式的本地包
+fn local_requirements(packages: &[String]) -> Vec<String> {
+    packages
+        .iter()
+        .filter(|i| {
+            i.parse::<requirement::Requirement>()
+                .is_ok_and(|i| i.url.is_some_and(|i| i.starts_with("file:")))
+        })
+        .cloned()
+        .collect()
+}
 
-    info!("安装结果");
-    pip_list(&targetdir)?;
+// 内置解析器使用的查找器，find_links 中的 URL 不受支持，会被忽略
+fn new_finder(
+    config: &config::Config,
+    pyver: &str,
+    cachedir: &std::path::Path,
+) -> Result<resolver::Finder> {
+    let mut dirs = vec![];
+    for i in config.find_links.iter() {
+        if i.contains("://") {
+            warn!("内置安装器不支持 URL，忽略：{}", i);
+            continue;
+        }
+        let mut p = std::path::PathBuf::from(i);
+        if p.is_relative() {
+            p = std::env::current_dir()?.join(p);
+        }
+        dirs.push(p);
+    }
+    let mut indexes = vec![];
+    if !config.no_index {
+        if config.pip_mirror.is_empty() {
+            indexes.push(resolver::DEFAULT_INDEX.to_string());
+        } else {
+            indexes.push(config.pip_mirror.clone());
+        }
+        indexes.extend(config.extra_index_urls.iter().cloned());
+    }
+    resolver::Finder::new(
+        &dirs,
+        &indexes,
+        pyver,
+        config.is32,
+        &cachedir.join("wheels"),
+    )
+}
 
-    warn!("正在清理 ...");
-    let keeppip = config.keep_pip
-        || config
-            .packages
-            .iter()
-            .any(|i| i == "pip" || i == "setuptools" || i == "wheel");
-    cleanup(
-        &targetdir,
-        keeppip,
-        config.keep_scripts,
-        config.keep_dist_info,
-    )?;
+// 使用本机 Python 编译，字节码只与 Python 次版本有关，版本不同时跳过
+fn native_compile(
+    config: &config::Config,
+    dir: &std::path::Path,
+    pyver: &str,
+    paths: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<Vec<CompileFailure>> {
+    if !host_python_matches(config, pyver) {
+        // 跳过编译时所有文件都没有编译，按编译失败的策略处理
+        if config.compile_failure == CheckPolicy::Fail {
+            return Err("无法使用本机 Python 编译，compile_failure 为 fail，停止构建".into());
+        }
+        return Ok(vec![]);
+    }
+    _py_compile(
+        &|| new_host_python_command(config),
+        config,
+        dir,
+        paths,
+        progress_callback,
+    )
+}
 
-    warn!("完成！");
-    Ok(())
+// 本机 Python 与目标的次版本是否相同，不同时输出警告
+fn host_python_matches(config: &config::Config, pyver: &str) -> bool {
+    let target: Vec<&str> = pyver.split('.').take(2).collect();
+    let target = target.join(".");
+    let mut cmd = new_host_python_command(config);
+    cmd.args(["-c", "import sys; print(\"%d.%d\" % sys.version_info[:2])"]);
+    let host = match cmd.output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        _ => {
+            warn!("找不到本机 Python，跳过编译");
+            return false;
+        }
+    };
+    if host != target {
+        warn!(
+            "本机 Python 版本为 {}，与目标版本 {} 不同，跳过编译",
+            host, target
+        );
+        return false;
+    }
+    true
+}
+
+// 使用 pip wheel 把本地目录或源码包构建为 wheel，保存到缓存目录中
+// 构建依赖从索引或 find_links 中取得
+fn build_wheel(
+    config: &config::Config,
+    mut cmd: std::process::Command,
+    cachedir: &std::path::Path,
+    source: &std::path::Path,
+    index_args: &[String],
+) -> Result<std::path::PathBuf> {
+    let wheels = cachedir.join("pyembed-local-wheels");
+    let out = wheels.join("build");
+    if out.exists() {
+        std::fs::remove_dir_all(&out)?;
+    }
+    std::fs::create_dir_all(&out)?;
+    cmd.args(["-m", "pip", "wheel", "--no-deps", "-w"]);
+    cmd.arg(&out);
+    cmd.args(pip_cache_args(config)?);
+    cmd.args(index_args);
+    if let Some(path) = pip_config_file(config)? {
+        cmd.env("PIP_CONFIG_FILE", path);
+    }
+    cmd.arg(source);
+    let mut process = cmd.spawn()?;
+    let (t1, t2) = process_output_to_log(&mut process);
+    let status = process.wait()?;
+    t1.join().unwrap();
+    t2.join().unwrap();
+    if !status.success() {
+        return Err(format!("构建 {} 失败 [{}]", source.display(), status).into());
+    }
+
+    let mut built = None;
+    for i in std::fs::read_dir(&out)? {
+        let path = i?.path();
+        if path.extension().is_some_and(|i| i == "whl") {
+            built = Some(path);
+        }
+    }
+    let built = match built {
+        Some(built) => built,
+        None => return Err(format!("构建 {} 没有生成 wheel", source.display()).into()),
+    };
+    let wheel = wheels.join(built.file_name().unwrap());
+    std::fs::rename(&built, &wheel)?;
+    std::fs::remove_dir_all(&out)?;
+    Ok(wheel)
+}
+
+// pip_cache 打开时的 pip 缓存目录，uv 使用同级的 uv-cache 目录
+fn pip_cache_dir(config: &config::Config) -> Result<Option<std::path::PathBuf>> {
+    if !config.pip_cache {
+        return Ok(None);
+    }
+    Ok(Some(
+        std::env::current_dir()?
+            .join(&config.cache_dir)
+            .join("pip-cache"),
+    ))
+}
+
+fn pip_cache_args(config: &config::Config) -> Result<Vec<String>> {
+    Ok(match pip_cache_dir(config)? {
+        Some(dir) => vec!["--cache-dir".to_string(), dir.to_string_lossy().to_string()],
+        None => vec!["--no-cache-dir".to_string()],
+    })
+}
+
+// 运行 pip 时的工作目录是运行时目录，需要转为绝对路径
+fn pip_config_file(config: &config::Config) -> Result<Option<std::path::PathBuf>> {
+    match &config.pip_config {
+        Some(path) if path.is_relative() => Ok(Some(std::env::current_dir()?.join(path))),
+        Some(path) => Ok(Some(path.clone())),
+        None => Ok(None),
+    }
 }
 
 fn is_empty_dir(dir: &std::path::Path) -> Result<bool> {
@@ -168,101 +1268,100 @@ fn is_empty_dir(dir: &std::path::Path) -> Result<bool> {
     return Ok(true);
 }
 
-fn compile(dir: &std::path::Path, optimize: u8) -> Result<()> {
-    _py_compile(dir, optimize)
-}
-
-fn _py_compile(dir: &std::path::Path, optimize: u8) -> Result<()> {
-    let script = r#"
-# encoding: utf-8
-
-import sys
-import os
-import py_compile
-import shutil
-
-ROOT = sys.argv[1]
-OPTIMIZE = int(sys.argv[2])
-FOUND_PYD = False
-
-def compile(dir):
-    sitedir = os.path.join(ROOT, "Lib", "site-packages")
-    for i in os.listdir(os.path.join(sitedir, dir)):
-        fullname = os.path.join(sitedir, dir, i)
-        shortname = os.path.join(dir, i)
-        if os.path.isdir(fullname):
-            if i.lower() == "__pycache__":
-                print(f"删除：{shortname}")
-                shutil.rmtree(fullname)
-            elif i.lower().endswith(".dist-info"):
-                print(f"跳过：{shortname}")
-            else:
-                compile(shortname)
-        elif os.path.isfile(fullname):
-            if i.lower().endswith(".py"):
-                print(f"编译：{shortname}")
-                try:
-                    py_compile.compile(fullname, cfile=fullname + "c", dfile=shortname, doraise=True, optimize=OPTIMIZE)
-                except:
-                    print(f"编译失败，跳过：{shortname}")
-                else:
-                    os.remove(fullname)
-            elif i.lower().endswith(".pyd"):
-                global FOUND_PYD
-                FOUND_PYD = True
-        else:
-            print(f"未知文件类型：{shortname}")
-
-compile(".")
-if not FOUND_PYD:
-    print("==========")
-    print("没有发现 .pyd 文件，site-packages 目录也许可以被打包为 zip")
-"#;
-
-    use std::io::Write;
-    let mut cmd = new_python_command(dir);
-    cmd.arg("-");
-    cmd.arg(dir);
-    cmd.arg(optimize.to_string());
-    cmd.stdin(std::process::Stdio::piped());
-    let mut process = cmd.spawn()?;
-    process
-        .stdin
-        .as_mut()
-        .unwrap()
-        .write_all(script.as_bytes())?;
-    let (t1, t2) = process_output_to_log(&mut process);
-    let status = process.wait()?;
-    t1.join().unwrap();
-    t2.join().unwrap();
-    if !status.success() {
-        return Err(format!("编译失败 [{}]", status).into());
+// 不能运行目标 python.exe 时使用本机 Python 编译
+fn compile_runtime(
+    config: &config::Config,
+    dir: &std::path::Path,
+    pyver: &str,
+    paths: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<Vec<CompileFailure>> {
+    if config.installer == Backend::Native || !cfg!(windows) {
+        native_compile(config, dir, pyver, paths, progress_callback)
+    } else {
+        compile(config, dir, paths, progress_callback)
     }
-    Ok(())
+}
+
+fn compile(
+    config: &config::Config,
+    dir: &std::path::Path,
+    paths: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<Vec<CompileFailure>> {
+    _py_compile(
+        &|| new_python_command(dir),
+        config,
+        dir,
+        paths,
+        progress_callback,
+    )
+}
+
+fn _py_compile(
+    new_python: &dyn Fn() -> std::process::Command,
+    config: &config::Config,
+    dir: &std::path::Path,
+    paths: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<Vec<CompileFailure>> {
+    let summary = compile::compile(new_python, config, dir, paths, progress_callback)?;
+    let zip = config.zip_site_packages || !config.zip_packages.is_empty();
+    if paths.is_empty() && !summary.found_pyd && !zip {
+        info!("==========");
+        info!("没有发现 .pyd 文件，site-packages 目录也许可以被打包为 zip");
+    }
+    // 编译失败的文件保留源文件
+    if config.compile_failure != CheckPolicy::Ignore {
+        for i in summary.failed.iter() {
+            error!("编译失败，保留源文件：{}", i.file);
+            error!("    {}", i.error);
+        }
+    }
+    if !summary.failed.is_empty() && config.compile_failure == CheckPolicy::Fail {
+        return Err(format!("{} 个文件编译失败", summary.failed.len()).into());
+    }
+    Ok(summary.failed)
 }
 
 fn cleanup(
+    config: &config::Config,
     dir: &std::path::Path,
     keeppip: bool,
-    keepscripts: bool,
-    keepdistinfo: bool,
+    installer: Option<&dyn installer::Installer>,
 ) -> Result<()> {
     if !keeppip {
-        pip_uninstall(dir, &["setuptools", "wheel", "pip"])?;
+        match installer {
+            Some(installer) => installer.uninstall(&["setuptools", "wheel", "pip"])?,
+            None => {
+                for i in ["setuptools", "wheel", "pip"] {
+                    wheel::uninstall(dir, i)?;
+                }
+            }
+        }
     }
     let mut rmdirs = vec![];
-    if !keepscripts {
+    if !config.keep_scripts {
         let scripts = dir.join("Scripts");
         if scripts.exists() {
             rmdirs.push(scripts);
         }
     }
-    if !keepdistinfo {
-        let site = dir.join("Lib").join("site-packages");
-        for i in std::fs::read_dir(site)? {
-            let i = i?;
-            let path = i.path();
-            if path.is_dir() && i.file_name().to_string_lossy().ends_with(".dist-info") {
+    if !config.keep_dist_info {
+        let keep: Vec<String> = config
+            .keep_dist_info_for
+            .iter()
+            .map(|i| pyproject::normalize_name(i))
+            .collect();
+        for path in metadata::dist_infos(&dir.join("Lib").join("site-packages"))? {
+            let name = metadata::read(&path)
+                .map(|i| pyproject::normalize_name(&i.name))
+                .unwrap_or_default();
+            if keep.contains(&name) {
+                info!("保留：{}", path.display());
+            } else if config.minimal_dist_info {
+                minimize_dist_info(&path)?;
+            } else {
                 rmdirs.push(path);
             }
         }
@@ -274,27 +1373,29 @@ fn cleanup(
     Ok(())
 }
 
-fn setup_pip(dir: &std::path::Path, pip: &std::path::Path, mirror: Option<&str>) -> Result<()> {
-    let mut cmd = new_python_command(dir);
-    cmd.arg(pip);
-    cmd.args(&["--no-cache-dir", "--no-warn-script-location"]);
-    if let Some(mirror) = mirror {
-        cmd.args(&["-i", mirror]);
-    }
-    let mut process = cmd.spawn()?;
-    let (t1, t2) = process_output_to_log(&mut process);
-    let status = process.wait()?;
-    t1.join().unwrap();
-    t2.join().unwrap();
-    if !status.success() {
-        return Err(format!("安装 pip 失败 [{}]", status).into());
+// 只保留 importlib.metadata 需要的文件，没有 RECORD 后不能再由 pip 卸载
+fn minimize_dist_info(dist_info: &std::path::Path) -> Result<()> {
+    info!("精简：{}", dist_info.display());
+    for i in std::fs::read_dir(dist_info)? {
+        let i = i?;
+        let path = i.path();
+        let name = i.file_name().to_string_lossy().to_string();
+        if name == "METADATA" {
+            let text = String::from_utf8_lossy(&std::fs::read(&path)?).to_string();
+            std::fs::write(&path, metadata::trim(&text))?;
+        } else if name == "entry_points.txt" || name == "top_level.txt" {
+            continue;
+        } else if path.is_dir() {
+            std::fs::remove_dir_all(&path)?;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
     }
     Ok(())
 }
 
-fn ensure_pth(dir: &std::path::Path) -> Result<()> {
-    let (major, minor, _) = get_local_python_version(dir)?;
-    let pth = dir.join(format!("python{}{}._pth", major, minor));
+fn ensure_pth(dir: &std::path::Path, pyver: &str) -> Result<()> {
+    let pth = dir.join(format!("python{}._pth", utility::python_xy(pyver)));
     if !pth.exists() {
         return Err("pth 文件不存在".into());
     }
@@ -323,7 +1424,18 @@ fn ensure_pth(dir: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
-fn get_local_python_version(dir: &std::path::Path) -> Result<(u8, u8, u8)> {
+fn get_local_python_version(dir: &std::path::Path, native: bool) -> Result<(u8, u8, u8)> {
+    // 不能运行 python.exe 时从文件版本信息中读取，如 3.11.4 的文件版本为 3.11.4150.1013
+    if native || !cfg!(windows) {
+        let exe = dir.join("python.exe");
+        if !exe.exists() {
+            return Err(format!("{} 不存在", exe.display()).into());
+        }
+        return match utility::pe_file_version(&std::fs::read(exe)?) {
+            Some((major, minor, build, _)) => Ok((major as u8, minor as u8, (build / 1000) as u8)),
+            None => Err("无法获取本地 python 版本".into()),
+        };
+    }
     let mut cmd = new_python_command(dir);
     cmd.args(&[
         "-c",
@@ -339,14 +1451,34 @@ fn get_local_python_version(dir: &std::path::Path) -> Result<(u8, u8, u8)> {
 }
 
 fn new_python_command(dir: &std::path::Path) -> std::process::Command {
-    use std::os::windows::process::CommandExt;
-    let mut cmd = std::process::Command::new(dir.join("python.exe"));
-    cmd.env("PYTHONIOENCODING", "utf-8");
+    let mut cmd = new_command(dir.join("python.exe"));
     cmd.current_dir(dir);
+    cmd
+}
+
+// 本机的 Python，用于运行与目标运行时无关的 pip download 等操作
+fn new_host_python_command(config: &config::Config) -> std::process::Command {
+    let python = if !config.host_python.is_empty() {
+        config.host_python.as_str()
+    } else if cfg!(windows) {
+        "python"
+    } else {
+        "python3"
+    };
+    new_command(python)
+}
+
+fn new_command(program: impl AsRef<std::ffi::OsStr>) -> std::process::Command {
+    let mut cmd = std::process::Command::new(program);
+    cmd.env("PYTHONIOENCODING", "utf-8");
     cmd.stdin(std::process::Stdio::null());
     cmd.stdout(std::process::Stdio::piped());
     cmd.stderr(std::process::Stdio::piped());
-    cmd.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
+    }
     cmd
 }
 
@@ -379,44 +1511,34 @@ fn process_output_to_log(
     (t1, t2)
 }
 
-fn pip_install<I, S>(dir: &std::path::Path, pkgnames: I, mirror: Option<&str>) -> Result<()>
+// 按目标平台下载 wheel，不能使用源码包
+fn pip_download<I, S>(
+    config: &config::Config,
+    dest: &std::path::Path,
+    pyver: &str,
+    pkgnames: I,
+    index_args: &[String],
+) -> Result<()>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<std::ffi::OsStr>,
 {
-    let mut cmd = new_python_command(dir);
-    cmd.args(&[
-        "-m",
-        "pip",
-        "install",
-        "--no-cache-dir",
-        "--no-warn-script-location",
-        "-U",
+    let v: Vec<&str> = pyver.split('.').take(2).collect();
+    let mut cmd = new_host_python_command(config);
+    cmd.args(["-m", "pip", "download", "--only-binary", ":all:"]);
+    cmd.args(pip_cache_args(config)?);
+    cmd.args(["--implementation", "cp", "--python-version", &v.join(".")]);
+    cmd.args([
+        "--platform",
+        if config.is32 { "win32" } else { "win_amd64" },
     ]);
-    if let Some(mirror) = mirror {
-        cmd.args(&["-i", mirror]);
-    }
-    for i in pkgnames {
-        cmd.arg(i);
-    }
-    let mut process = cmd.spawn()?;
-    let (t1, t2) = process_output_to_log(&mut process);
-    let status = process.wait()?;
-    t1.join().unwrap();
-    t2.join().unwrap();
-    if !status.success() {
-        return Err(format!("安装依赖包失败 [{}]", status).into());
+    cmd.arg("--dest");
+    cmd.arg(dest);
+    cmd.args(index_args);
+    cmd.args(&config.pip_args);
+    if let Some(path) = pip_config_file(config)? {
+        cmd.env("PIP_CONFIG_FILE", path);
     }
-    Ok(())
-}
-
-fn pip_uninstall<I, S>(dir: &std::path::Path, pkgnames: I) -> Result<()>
-where
-    I: IntoIterator<Item = S>,
-    S: AsRef<std::ffi::OsStr>,
-{
-    let mut cmd = new_python_command(dir);
-    cmd.args(&["-m", "pip", "uninstall", "-y"]);
     for i in pkgnames {
         cmd.arg(i);
     }
@@ -426,29 +1548,7 @@ where
     t1.join().unwrap();
     t2.join().unwrap();
     if !status.success() {
-        return Err(format!("卸载依赖包失败 [{}]", status).into());
-    }
-    Ok(())
-}
-
-fn pip_list(dir: &std::path::Path) -> Result<()> {
-    let mut cmd = new_python_command(dir);
-    cmd.args(&["-m", "pip", "list"]);
-    cmd.args(&["--format", "columns"]);
-    let output = cmd.output()?;
-    let stdout = String::from_utf8(output.stdout)?;
-    if !output.status.success() {
-        info!("{}", stdout);
-        return Err(format!("pip list 失败 [{}]", output.status).into());
-    } else {
-        // 从结果中过滤掉 pip、setuptools、wheel
-        for i in stdout.lines().filter(|line| {
-            !line.starts_with("pip ")
-                && !line.starts_with("setuptools ")
-                && !line.starts_with("wheel ")
-        }) {
-            info!("{}", i);
-        }
+        return Err(format!("下载依赖包失败 [{}]", status).into());
     }
     Ok(())
 }
@@ -0,0 +1,158 @@
+// packages 中的本地包：wheel 文件、源码包、项目目录或 file:// URL
+// 安装器的工作目录是运行时目录，相对路径必须事先按调用目录或 pyproject.toml 所在目录转为绝对路径
+
+use crate::wheel::WheelName;
+use crate::Result;
+
+#[derive(Debug, Clone)]
+pub(crate) struct LocalPackage {
+    // 绝对路径
+    pub path: std::path::PathBuf,
+    pub extras: Vec<String>,
+    pub marker: Option<String>,
+}
+
+impl LocalPackage {
+    // 不是本地包时返回 None，是本地包但路径不存在或无法安装时返回错误
+    // 支持 ./dist/a.whl、../mylib[extra]、file:///c:/a.whl 和 name @ file:///c:/a.whl 几种形式
+    pub fn parse(spec: &str, base: &std::path::Path) -> Result<Option<LocalPackage>> {
+        let spec = spec.trim();
+        let direct =
+            crate::utility::regex_find(r"^[A-Za-z0-9][A-Za-z0-9._-]*\s*(?:\[[^\]]*\])?\s*@", spec);
+        let (path, extras, marker) = if direct.is_some() {
+            let req: crate::requirement::Requirement = spec.parse()?;
+            let url = req.url.unwrap_or_default();
+            let path = match url.strip_prefix("file:") {
+                Some(_) => file_url_to_path(&url)?,
+                // 远程 URL 原样交给安装器
+                None if url.contains("://") => return Ok(None),
+                None => std::path::PathBuf::from(&url),
+            };
+            (path, req.extras, req.marker)
+        } else {
+            let (rest, marker) = match spec.split_once(';') {
+                Some((rest, marker)) => (rest.trim(), Some(marker.trim().to_string())),
+                None => (spec, None),
+            };
+            let (rest, extras) = match crate::utility::regex_find(r"^(.*?)\[([^\]]*)\]$", rest) {
+                Some(caps) => (
+                    caps.get(1).unwrap().as_str().to_string(),
+                    caps.get(2)
+                        .unwrap()
+                        .as_str()
+                        .split(',')
+                        .map(crate::pyproject::normalize_name)
+                        .filter(|i| !i.is_empty())
+                        .collect(),
+                ),
+                None => (rest.to_string(), vec![]),
+            };
+            let path = if rest.starts_with("file:") {
+                file_url_to_path(&rest)?
+            } else if is_path_like(&rest) {
+                std::path::PathBuf::from(&rest)
+            } else {
+                return Ok(None);
+            };
+            (path, extras, marker.filter(|i| !i.is_empty()))
+        };
+
+        let path = if path.is_relative() {
+            base.join(path)
+        } else {
+            path
+        };
+        if !path.exists() {
+            return Err(format!("本地包不存在：{}", path.display()).into());
+        }
+        let package = LocalPackage {
+            path,
+            extras,
+            marker,
+        };
+        if package.path.is_dir() {
+            if !package.path.join("pyproject.toml").exists()
+                && !package.path.join("setup.py").exists()
+            {
+                return Err(format!(
+                    "{} 中没有 pyproject.toml 或 setup.py，不是 Python 项目",
+                    package.path.display()
+                )
+                .into());
+            }
+        } else if package.is_wheel() {
+            package.wheel_name(&package.path)?;
+        } else if !package.is_sdist() {
+            return Err(format!("不支持的本地包：{}", package.path.display()).into());
+        }
+        Ok(Some(package))
+    }
+
+    pub fn is_wheel(&self) -> bool {
+        self.path.is_file() && self.file_name().ends_with(".whl")
+    }
+
+    fn is_sdist(&self) -> bool {
+        let name = self.file_name();
+        self.path.is_file() && (name.ends_with(".tar.gz") || name.ends_with(".zip"))
+    }
+
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|i| i.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    }
+
+    fn wheel_name(&self, wheel: &std::path::Path) -> Result<WheelName> {
+        let filename = wheel.file_name().unwrap().to_string_lossy();
+        filename
+            .parse()
+            .map_err(|_| format!("无效的 wheel 文件名：{}", filename).into())
+    }
+
+    // 转为 pip、uv 和内置解析器都接受的 name[extras] @ file:///... 形式
+    // wheel 为本地包本身或由它构建出的 wheel，需要与目标平台兼容
+    pub fn requirement(&self, wheel: &std::path::Path, python: &str, is32: bool) -> Result<String> {
+        let name = self.wheel_name(wheel)?;
+        if name.score(python, is32).is_none() {
+            return Err(format!(
+                "{} 与目标平台不兼容",
+                wheel.file_name().unwrap().to_string_lossy()
+            )
+            .into());
+        }
+        let url = match reqwest::Url::from_file_path(std::fs::canonicalize(wheel)?) {
+            Ok(url) => url,
+            Err(_) => return Err(format!("无法转为 URL：{}", wheel.display()).into()),
+        };
+        let mut result = name.name;
+        if !self.extras.is_empty() {
+            result = format!("{}[{}]", result, self.extras.join(","));
+        }
+        result = format!("{} @ {}", result, url);
+        if let Some(marker) = &self.marker {
+            result = format!("{} ; {}", result, marker);
+        }
+        Ok(result)
+    }
+}
+
+fn file_url_to_path(url: &str) -> Result<std::path::PathBuf> {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|i| i.to_file_path().ok())
+        .ok_or_else(|| format!("无效的 file URL：{}", url).into())
+}
+
+// 包名中不会出现路径分隔符，也不会以 . 开头
+fn is_path_like(s: &str) -> bool {
+    let lower = s.to_ascii_lowercase();
+    s.starts_with('.')
+        || s.contains('/')
+        || s.contains('\\')
+        || s.chars().nth(1) == Some(':')
+        || lower.ends_with(".whl")
+        || lower.ends_with(".tar.gz")
+        || lower.ends_with(".zip")
+}
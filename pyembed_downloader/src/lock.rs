@@ -0,0 +1,202 @@
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+pub(crate) const LOCK_VERSION: u32 = 1;
+
+// pyembed.lock 文件内容
+// 记录一次构建所使用的 Python 版本、安装包哈希和所有安装的依赖包，用于复现同样的运行时
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Lock {
+    pub version: u32,
+    pub python: String,
+    pub arch: String,
+    pub archive: Option<Archive>,
+    // 用户请求安装的依赖，本地包保留配置中的原始写法，用于判断锁文件是否过期
+    pub requested: Vec<String>,
+    #[serde(rename = "package", default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Archive {
+    pub name: String,
+    pub md5: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub hashes: Vec<String>,
+}
+
+impl Lock {
+    pub fn load(path: &std::path::Path) -> Result<Lock> {
+        if !path.exists() {
+            return Err(format!("锁文件 {} 不存在", path.display()).into());
+        }
+        let lock: Lock = toml::from_str(&std::fs::read_to_string(path)?)?;
+        if lock.version != LOCK_VERSION {
+            return Err(format!("不支持的锁文件版本：{}", lock.version).into());
+        }
+        Ok(lock)
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<()> {
+        let content = format!(
+            "# 此文件由 pyembed_downloader 自动生成，请勿手动修改\n\n{}",
+            toml::to_string(self)?
+        );
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    // installed 为 pip inspect 的输出，reports 为本次 pip install --report 的输出
+    // 哈希优先从本次安装报告中获取，其次从 known 中获取相同版本的记录
+    pub fn new(
+        python: &str,
+        arch: &str,
+        archive: Option<Archive>,
+        requested: &[String],
+        installed: &serde_json::Value,
+        reports: &[serde_json::Value],
+        known: &[LockedPackage],
+    ) -> Lock {
+        let requested_names: Vec<String> = requested
+            .iter()
+            .filter_map(|i| requirement_name(i))
+            .collect();
+        let mut packages = vec![];
+        for dist in installed["installed"].as_array().into_iter().flatten() {
+            let name = dist["metadata"]["name"].as_str().unwrap_or_default();
+            let version = dist["metadata"]["version"].as_str().unwrap_or_default();
+            if name.is_empty() || version.is_empty() {
+                continue;
+            }
+            let key = normalize_name(name);
+            let item = reports
+                .iter()
+                .flat_map(|i| i["install"].as_array().into_iter().flatten())
+                .find(|i| {
+                    normalize_name(i["metadata"]["name"].as_str().unwrap_or_default()) == key
+                });
+            // pip、setuptools、wheel 使用 pip 安装器时有安装报告，会记录在锁文件中
+            // 其他情况下由 get-pip.py 或 uv 引导安装，除非作为依赖安装，否则不记录
+            if matches!(key.as_str(), "pip" | "setuptools" | "wheel")
+                && item.is_none()
+                && !requested_names.contains(&key)
+            {
+                continue;
+            }
+            let mut package = LockedPackage {
+                name: key.clone(),
+                version: version.to_string(),
+                url: None,
+                hashes: vec![],
+            };
+            if let Some(item) = item {
+                let info = &item["download_info"];
+                package.url = info["url"].as_str().map(|i| i.to_string());
+                if let Some(hashes) = info["archive_info"]["hashes"].as_object() {
+                    for (k, v) in hashes {
+                        if let Some(v) = v.as_str() {
+                            package.hashes.push(format!("{}:{}", k, v));
+                        }
+                        // uv 的解析结果中同一版本可能有多个文件的哈希
+                        for v in v
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|i| i.as_str())
+                        {
+                            package.hashes.push(format!("{}:{}", k, v));
+                        }
+                    }
+                } else if let Some(hash) = info["archive_info"]["hash"].as_str() {
+                    package.hashes.push(hash.replacen('=', ":", 1));
+                }
+            } else if let Some(old) = known.iter().find(|i| i.name == key && i.version == version) {
+                package.url = old.url.clone();
+                package.hashes = old.hashes.clone();
+            }
+            packages.push(package);
+        }
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut requested = requested.to_vec();
+        requested.sort();
+        Lock {
+            version: LOCK_VERSION,
+            python: python.to_string(),
+            arch: arch.to_string(),
+            archive,
+            requested,
+            packages,
+        }
+    }
+
+    // 检查锁文件是否与当前配置一致，不一致则认为锁文件已过期
+    pub fn check(
+        &self,
+        python: &str,
+        arch: &str,
+        archive: Option<&Archive>,
+        requested: &[String],
+    ) -> Result<()> {
+        if self.python != python {
+            return Err(format!(
+                "锁文件已过期：Python 版本为 {}，当前为 {}",
+                self.python, python
+            )
+            .into());
+        }
+        if self.arch != arch {
+            return Err(format!("锁文件已过期：架构为 {}，当前为 {}", self.arch, arch).into());
+        }
+        if let (Some(a), Some(b)) = (&self.archive, archive) {
+            if a != b {
+                return Err(format!("锁文件已过期：{} 的哈希不一致", b.name).into());
+            }
+        }
+        let mut requested = requested.to_vec();
+        requested.sort();
+        if self.requested != requested {
+            return Err("锁文件已过期：要安装的依赖包与锁文件记录的不一致".into());
+        }
+        Ok(())
+    }
+}
+
+// 生成供 pip install --require-hashes 使用的 requirements 文件
+pub(crate) fn requirements(packages: &[LockedPackage]) -> Result<String> {
+    let mut result = String::new();
+    for i in packages.iter() {
+        if i.hashes.is_empty() {
+            return Err(format!("锁文件中 {} 没有记录哈希，无法按锁文件安装", i.name).into());
+        }
+        result += &format!("{}=={}", i.name, i.version);
+        for hash in i.hashes.iter() {
+            result += &format!(" \\\n    --hash={}", hash);
+        }
+        result += "\n";
+    }
+    Ok(result)
+}
+
+// 生成 pip install -c 使用的约束文件，固定每个包的版本
+pub(crate) fn constraints(packages: &[LockedPackage]) -> String {
+    packages
+        .iter()
+        .map(|i| format!("{}=={}\n", i.name, i.version))
+        .collect()
+}
+
+// 取得依赖声明中的包名，本地路径、URL 等返回 None
+pub(crate) fn requirement_name(req: &str) -> Option<String> {
+    let caps =
+        crate::utility::regex_find(r"^\s*([A-Za-z0-9][A-Za-z0-9._-]*)\s*([\[;<>=!~ ]|$)", req)?;
+    Some(normalize_name(caps.get(1)?.as_str()))
+}
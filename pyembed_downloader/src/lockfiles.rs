@@ -0,0 +1,766 @@
+// 导入其他工具生成的锁文件：poetry.lock、uv.lock、Pipfile.lock
+// 按目标运行时的环境标记筛选依赖包，得到要安装的精确版本和哈希
+
+use crate::lock::LockedPackage;
+use crate::markers::Environment;
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+pub(crate) fn import(
+    path: &std::path::Path,
+    env: &Environment,
+    extras: &[String],
+) -> Result<Vec<LockedPackage>> {
+    if !path.exists() {
+        return Err(format!("{} 不存在", path.display()).into());
+    }
+    let filename = path
+        .file_name()
+        .map(|i| i.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let content = std::fs::read_to_string(path)?;
+    let mut packages = match filename.as_str() {
+        "poetry.lock" => {
+            let pyproject = std::fs::read_to_string(path.with_file_name("pyproject.toml")).ok();
+            import_poetry(&content, pyproject.as_deref(), env, extras)?
+        }
+        "uv.lock" => import_uv(&content, env, extras)?,
+        "pipfile.lock" => import_pipfile(&content, env)?,
+        _ => {
+            return Err(format!(
+                "无法识别的锁文件：{}，仅支持 poetry.lock、uv.lock、Pipfile.lock",
+                path.display()
+            )
+            .into())
+        }
+    };
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    for i in packages.windows(2) {
+        if i[0].name == i[1].name {
+            return Err(format!(
+                "锁文件中 {} 有多个版本（{}、{}）适用于目标环境",
+                i[0].name, i[0].version, i[1].version
+            )
+            .into());
+        }
+    }
+    Ok(packages)
+}
+
+// 依赖关系中的一条边
+struct Edge {
+    from: usize,
+    to: String,
+    // 这条边在目标环境中是否成立
+    active: bool,
+}
+
+// 从入口出发，保留至少有一条成立的依赖边可以到达的包
+// 没有任何包依赖的包视为入口
+fn reachable(count: usize, names: &[String], edges: &[Edge], roots: Option<&[usize]>) -> Vec<bool> {
+    let mut result = vec![false; count];
+    let mut queue: Vec<usize> = match roots {
+        Some(roots) => roots.to_vec(),
+        None => (0..count)
+            .filter(|&i| !edges.iter().any(|e| e.to == names[i] && e.from != i))
+            .collect(),
+    };
+    while let Some(i) = queue.pop() {
+        if result[i] {
+            continue;
+        }
+        result[i] = true;
+        for e in edges.iter().filter(|e| e.from == i && e.active) {
+            for (j, name) in names.iter().enumerate() {
+                if *name == e.to && !result[j] {
+                    queue.push(j);
+                }
+            }
+        }
+    }
+    result
+}
+
+fn evaluate(env: &Environment, marker: Option<&str>) -> Result<bool> {
+    evaluate_with_extras(env, marker, &[])
+}
+
+fn evaluate_with_extras(
+    env: &Environment,
+    marker: Option<&str>,
+    extras: &[String],
+) -> Result<bool> {
+    match marker {
+        Some(marker) if !marker.trim().is_empty() => env.evaluate(marker, extras),
+        _ => Ok(true),
+    }
+}
+
+// pyproject 为 poetry.lock 同目录的 pyproject.toml，从中读取直接依赖作为入口
+// lock-version 2.1 起每个包都记录了环境标记，直接按标记筛选，不需要入口
+fn import_poetry(
+    content: &str,
+    pyproject: Option<&str>,
+    env: &Environment,
+    extras: &[String],
+) -> Result<Vec<LockedPackage>> {
+    let doc: toml::Table = content.parse()?;
+    let lock_version: Vec<u64> = doc
+        .get("metadata")
+        .and_then(|i| i.get("lock-version"))
+        .and_then(|i| i.as_str())
+        .unwrap_or_default()
+        .split('.')
+        .filter_map(|i| i.parse().ok())
+        .collect();
+    let has_markers = lock_version >= vec![2, 1];
+    let by_roots = !has_markers && pyproject.is_some();
+    let packages: Vec<&toml::Table> = doc
+        .get("package")
+        .and_then(|i| i.as_array())
+        .map(|i| i.iter().filter_map(|i| i.as_table()).collect())
+        .unwrap_or_default();
+    // 旧版本的 poetry.lock 把文件哈希放在 [metadata.files] 中
+    let metadata_files = doc
+        .get("metadata")
+        .and_then(|i| i.get("files"))
+        .and_then(|i| i.as_table());
+
+    let mut names = vec![];
+    let mut selected = vec![];
+    for pkg in packages.iter() {
+        let name = normalize_name(pkg.get("name").and_then(|i| i.as_str()).unwrap_or_default());
+        let main = match pkg.get("groups").and_then(|i| i.as_array()) {
+            Some(groups) => groups.iter().any(|i| i.as_str() == Some("main")),
+            None => {
+                pkg.get("category")
+                    .and_then(|i| i.as_str())
+                    .unwrap_or("main")
+                    == "main"
+            }
+        };
+        let optional = pkg
+            .get("optional")
+            .and_then(|i| i.as_bool())
+            .unwrap_or(false);
+        // 新版本的 poetry.lock 直接记录了包的环境标记，可能按分组区分
+        let marker = match pkg.get("markers") {
+            Some(toml::Value::String(s)) => Some(s.as_str()),
+            Some(toml::Value::Table(t)) => t.get("main").and_then(|i| i.as_str()),
+            _ => None,
+        };
+        // 可选的包只能通过 extras 安装，有标记时由标记中的 extra 条件决定，有入口时由可达性决定
+        let optional = optional && !(has_markers && marker.is_some()) && !by_roots;
+        selected.push(main && !optional && evaluate_with_extras(env, marker, extras)?);
+        names.push(name);
+    }
+
+    let mut edges = vec![];
+    for (i, pkg) in packages.iter().enumerate() {
+        if let Some(deps) = pkg.get("dependencies").and_then(|i| i.as_table()) {
+            for (name, spec) in deps {
+                let specs = match spec {
+                    toml::Value::Array(a) => a.iter().collect(),
+                    _ => vec![spec],
+                };
+                let mut active = false;
+                for spec in specs {
+                    let marker = spec.get("markers").and_then(|i| i.as_str());
+                    let optional = spec
+                        .get("optional")
+                        .and_then(|i| i.as_bool())
+                        .unwrap_or(false);
+                    let python = match spec.get("python").and_then(|i| i.as_str()) {
+                        Some(python) => python_matches(env, python),
+                        None => true,
+                    };
+                    if !optional && python && evaluate(env, marker)? {
+                        active = true;
+                    }
+                }
+                edges.push(Edge {
+                    from: i,
+                    to: normalize_name(name),
+                    active,
+                });
+            }
+        }
+    }
+    let reach = match pyproject {
+        _ if has_markers => vec![true; packages.len()],
+        Some(pyproject) => {
+            let requested = poetry_requested(pyproject, env, extras)?;
+            let roots: Vec<usize> = (0..packages.len())
+                .filter(|&i| requested.contains(&names[i]))
+                .collect();
+            reachable(packages.len(), &names, &edges, Some(&roots))
+        }
+        None => reachable(packages.len(), &names, &edges, None),
+    };
+
+    let mut result = vec![];
+    for (i, pkg) in packages.iter().enumerate() {
+        if !selected[i] || !reach[i] {
+            continue;
+        }
+        let version = pkg
+            .get("version")
+            .and_then(|i| i.as_str())
+            .unwrap_or_default();
+        if let Some(source) = pkg.get("source").and_then(|i| i.as_table()) {
+            let kind = source
+                .get("type")
+                .and_then(|i| i.as_str())
+                .unwrap_or_default();
+            if kind != "legacy" {
+                return Err(format!("{} 的来源为 {}，暂不支持", names[i], kind).into());
+            }
+        }
+        let files = match pkg.get("files") {
+            Some(files) => files.as_array(),
+            None => metadata_files
+                .and_then(|t| t.get(pkg.get("name").and_then(|i| i.as_str()).unwrap_or_default()))
+                .and_then(|i| i.as_array()),
+        };
+        let hashes = files
+            .into_iter()
+            .flatten()
+            .filter_map(|f| f.get("hash").and_then(|i| i.as_str()))
+            .map(|i| i.to_string())
+            .collect();
+        result.push(LockedPackage {
+            name: names[i].clone(),
+            version: version.to_string(),
+            url: None,
+            hashes,
+        });
+    }
+    Ok(result)
+}
+
+// pyproject.toml 中在目标环境下生效的直接依赖，包括 extras 中的可选依赖
+// 同时支持 [tool.poetry.dependencies] 和 poetry 2 使用的 [project]
+fn poetry_requested(content: &str, env: &Environment, extras: &[String]) -> Result<Vec<String>> {
+    let doc: toml::Table = content.parse()?;
+    let wanted: Vec<String> = extras.iter().map(|i| normalize_name(i)).collect();
+    let mut result = vec![];
+    if let Some(project) = doc.get("project") {
+        let mut deps: Vec<&toml::Value> = project
+            .get("dependencies")
+            .and_then(|i| i.as_array())
+            .map(|i| i.iter().collect())
+            .unwrap_or_default();
+        if let Some(optional) = project
+            .get("optional-dependencies")
+            .and_then(|i| i.as_table())
+        {
+            for (extra, list) in optional {
+                if wanted.contains(&normalize_name(extra)) {
+                    deps.extend(list.as_array().into_iter().flatten());
+                }
+            }
+        }
+        for dep in deps.into_iter().filter_map(|i| i.as_str()) {
+            let req: crate::requirement::Requirement = dep.parse()?;
+            if req.applies(env, &[])? {
+                result.push(normalize_name(&req.name));
+            }
+        }
+    }
+    let poetry = doc.get("tool").and_then(|i| i.get("poetry"));
+    // [tool.poetry.extras] 中按 extra 列出的可选依赖
+    let mut optional_wanted = vec![];
+    if let Some(table) = poetry
+        .and_then(|i| i.get("extras"))
+        .and_then(|i| i.as_table())
+    {
+        for (extra, list) in table {
+            if wanted.contains(&normalize_name(extra)) {
+                optional_wanted.extend(
+                    list.as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|i| i.as_str())
+                        .map(normalize_name),
+                );
+            }
+        }
+    }
+    if let Some(deps) = poetry
+        .and_then(|i| i.get("dependencies"))
+        .and_then(|i| i.as_table())
+    {
+        for (name, spec) in deps {
+            let name = normalize_name(name);
+            if name == "python" {
+                continue;
+            }
+            let specs = match spec {
+                toml::Value::Array(a) => a.iter().collect(),
+                _ => vec![spec],
+            };
+            for spec in specs {
+                let optional = spec
+                    .get("optional")
+                    .and_then(|i| i.as_bool())
+                    .unwrap_or(false);
+                let python = match spec.get("python").and_then(|i| i.as_str()) {
+                    Some(python) => python_matches(env, python),
+                    None => true,
+                };
+                let marker = spec.get("markers").and_then(|i| i.as_str());
+                if (!optional || optional_wanted.contains(&name))
+                    && python
+                    && evaluate(env, marker)?
+                {
+                    result.push(name);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+// poetry 的 python 约束可能是 ^3.8、>=3.7,<4.0、3.8 || 3.9 等形式
+// 能转换为 PEP 440 的按约束判断，无法识别的视为满足
+fn python_matches(env: &Environment, constraint: &str) -> bool {
+    let version = match env.python.parse::<crate::pep440::Version>() {
+        Ok(v) => v,
+        Err(_) => return true,
+    };
+    constraint.split("||").any(|part| {
+        let part = part.trim();
+        if part == "*" {
+            return true;
+        }
+        let spec = if let Some(v) = part.strip_prefix('^') {
+            let mut parts: Vec<u64> = v.split('.').filter_map(|i| i.parse().ok()).collect();
+            if parts.is_empty() {
+                return true;
+            }
+            let first = parts
+                .iter()
+                .position(|&i| i != 0)
+                .unwrap_or(parts.len() - 1);
+            parts.truncate(first + 1);
+            parts[first] += 1;
+            let upper: Vec<String> = parts.iter().map(|i| i.to_string()).collect();
+            format!(">={},<{}", v, upper.join("."))
+        } else if let Some(v) = part.strip_prefix('~').filter(|v| !v.starts_with('=')) {
+            let mut parts: Vec<u64> = v.split('.').filter_map(|i| i.parse().ok()).collect();
+            if parts.len() > 1 {
+                parts.truncate(2);
+                parts[1] += 1;
+            } else if let Some(i) = parts.first_mut() {
+                *i += 1;
+            }
+            let upper: Vec<String> = parts.iter().map(|i| i.to_string()).collect();
+            format!(">={},<{}", v, upper.join("."))
+        } else if part.starts_with(|c: char| c.is_ascii_digit()) {
+            // 只写版本号时匹配以此开头的版本，如 3.8 即 3.8.*
+            format!("=={}.*", part.trim_end_matches(".*"))
+        } else {
+            part.to_string()
+        };
+        spec.parse::<crate::pep440::Specifiers>()
+            .map(|s| s.contains(&version))
+            .unwrap_or(true)
+    })
+}
+
+fn import_uv(content: &str, env: &Environment, extras: &[String]) -> Result<Vec<LockedPackage>> {
+    let doc: toml::Table = content.parse()?;
+    let packages: Vec<&toml::Table> = doc
+        .get("package")
+        .and_then(|i| i.as_array())
+        .map(|i| i.iter().filter_map(|i| i.as_table()).collect())
+        .unwrap_or_default();
+
+    let mut names = vec![];
+    let mut roots = vec![];
+    let mut selected = vec![];
+    for (i, pkg) in packages.iter().enumerate() {
+        names.push(normalize_name(
+            pkg.get("name").and_then(|i| i.as_str()).unwrap_or_default(),
+        ));
+        let source = pkg.get("source").and_then(|i| i.as_table());
+        // 项目本身（editable 或 virtual）作为入口，但不安装
+        if source.is_some_and(|s| s.contains_key("editable") || s.contains_key("virtual")) {
+            roots.push(i);
+        }
+        // 分叉解析时，同一个包可能有多个版本，各自带有 resolution-markers
+        let mut ok = true;
+        if let Some(markers) = pkg.get("resolution-markers").and_then(|i| i.as_array()) {
+            ok = false;
+            for m in markers.iter().filter_map(|i| i.as_str()) {
+                if evaluate(env, Some(m))? {
+                    ok = true;
+                }
+            }
+        }
+        selected.push(ok);
+    }
+
+    let mut edges = vec![];
+    for (i, pkg) in packages.iter().enumerate() {
+        let mut deps: Vec<&toml::Value> = pkg
+            .get("dependencies")
+            .and_then(|i| i.as_array())
+            .map(|i| i.iter().collect())
+            .unwrap_or_default();
+        // 只有入口项目才按用户指定的 extras 展开
+        if roots.contains(&i) {
+            if let Some(optional) = pkg.get("optional-dependencies").and_then(|i| i.as_table()) {
+                for (extra, list) in optional {
+                    if extras
+                        .iter()
+                        .any(|e| normalize_name(e) == normalize_name(extra))
+                    {
+                        deps.extend(list.as_array().into_iter().flatten());
+                    }
+                }
+            }
+        }
+        for dep in deps {
+            let name = normalize_name(dep.get("name").and_then(|i| i.as_str()).unwrap_or_default());
+            let marker = dep.get("marker").and_then(|i| i.as_str());
+            edges.push(Edge {
+                from: i,
+                to: name,
+                active: evaluate(env, marker)?,
+            });
+        }
+    }
+    let roots = if roots.is_empty() { None } else { Some(roots) };
+    let reach = reachable(packages.len(), &names, &edges, roots.as_deref());
+
+    let mut result = vec![];
+    for (i, pkg) in packages.iter().enumerate() {
+        if !selected[i] || !reach[i] || roots.as_ref().is_some_and(|r| r.contains(&i)) {
+            continue;
+        }
+        let source = pkg.get("source").and_then(|i| i.as_table());
+        if let Some(source) = source {
+            if !source.contains_key("registry") {
+                let kind = source.keys().next().cloned().unwrap_or_default();
+                return Err(format!("{} 的来源为 {}，暂不支持", names[i], kind).into());
+            }
+        }
+        let mut hashes = vec![];
+        let files = pkg
+            .get("wheels")
+            .and_then(|i| i.as_array())
+            .into_iter()
+            .flatten()
+            .chain(pkg.get("sdist"));
+        for f in files {
+            if let Some(hash) = f.get("hash").and_then(|i| i.as_str()) {
+                hashes.push(hash.to_string());
+            }
+        }
+        result.push(LockedPackage {
+            name: names[i].clone(),
+            version: pkg
+                .get("version")
+                .and_then(|i| i.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            url: None,
+            hashes,
+        });
+    }
+    Ok(result)
+}
+
+fn import_pipfile(content: &str, env: &Environment) -> Result<Vec<LockedPackage>> {
+    let doc: serde_json::Value = serde_json::from_str(content)?;
+    let mut result = vec![];
+    if let Some(default) = doc["default"].as_object() {
+        for (name, info) in default {
+            if !evaluate(env, info["markers"].as_str())? {
+                continue;
+            }
+            let version = match info["version"].as_str() {
+                Some(v) => v.trim_start_matches("==").to_string(),
+                None => {
+                    return Err(format!(
+                        "{} 没有固定版本（可能来自 git 或本地路径），暂不支持",
+                        name
+                    )
+                    .into())
+                }
+            };
+            result.push(LockedPackage {
+                name: normalize_name(name),
+                version,
+                url: None,
+                hashes: info["hashes"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|i| i.as_str())
+                    .map(|i| i.to_string())
+                    .collect(),
+            });
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> Environment {
+        Environment::new("3.11.7", false)
+    }
+
+    fn names(packages: &[LockedPackage]) -> Vec<&str> {
+        let mut names: Vec<&str> = packages.iter().map(|i| i.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    const POETRY_LOCK: &str = r#"
+[[package]]
+name = "click"
+version = "8.1.7"
+optional = false
+python-versions = ">=3.7"
+groups = ["main"]
+files = [
+    {file = "click-8.1.7-py3-none-any.whl", hash = "sha256:aaaa"},
+    {file = "click-8.1.7.tar.gz", hash = "sha256:bbbb"},
+]
+
+[package.dependencies]
+colorama = {version = "*", markers = "platform_system == \"Windows\""}
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+optional = false
+groups = ["main"]
+files = [{file = "colorama-0.4.6-py2.py3-none-any.whl", hash = "sha256:cccc"}]
+
+[[package]]
+name = "legacy"
+version = "1.0"
+optional = false
+groups = ["main"]
+files = []
+
+[package.dependencies]
+typing-extensions = {version = ">=4", python = "<3.8"}
+
+[[package]]
+name = "typing-extensions"
+version = "4.8.0"
+optional = false
+groups = ["main"]
+files = []
+
+[[package]]
+name = "uvloop"
+version = "0.19.0"
+optional = false
+groups = ["main"]
+files = []
+
+[[package]]
+name = "pysocks"
+version = "1.7.1"
+optional = true
+groups = ["main"]
+files = []
+
+[[package]]
+name = "pytest"
+version = "7.4.0"
+optional = false
+groups = ["dev"]
+files = []
+
+[metadata]
+lock-version = "2.0"
+python-versions = "^3.8"
+"#;
+
+    const POETRY_PYPROJECT: &str = r#"
+[tool.poetry.dependencies]
+python = "^3.8"
+click = "^8.1"
+legacy = "^1.0"
+typing-extensions = "^4.8"
+uvloop = {version = "^0.19", markers = "sys_platform != 'win32'"}
+pysocks = {version = "^1.7", optional = true}
+
+[tool.poetry.extras]
+socks = ["pysocks"]
+
+[tool.poetry.group.dev.dependencies]
+pytest = "^7"
+"#;
+
+    #[test]
+    fn poetry() {
+        let packages = import_poetry(POETRY_LOCK, Some(POETRY_PYPROJECT), &env(), &[]).unwrap();
+        // typing-extensions 是直接依赖，即使 legacy 对它的依赖在目标环境中不成立也要保留
+        assert_eq!(
+            names(&packages),
+            ["click", "colorama", "legacy", "typing-extensions"]
+        );
+        let click = packages.iter().find(|i| i.name == "click").unwrap();
+        assert_eq!(click.version, "8.1.7");
+        assert_eq!(click.hashes, ["sha256:aaaa", "sha256:bbbb"]);
+
+        let extras = vec!["socks".to_string()];
+        let packages = import_poetry(POETRY_LOCK, Some(POETRY_PYPROJECT), &env(), &extras).unwrap();
+        assert!(names(&packages).contains(&"pysocks"));
+    }
+
+    #[test]
+    fn poetry_markers() {
+        // lock-version 2.1 起按每个包记录的标记筛选
+        let content = r#"
+[[package]]
+name = "click"
+version = "8.1.7"
+groups = ["main"]
+files = []
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+groups = ["main"]
+markers = "platform_system == \"Windows\""
+files = []
+
+[[package]]
+name = "uvloop"
+version = "0.19.0"
+groups = ["main"]
+markers = "sys_platform != \"win32\""
+files = []
+
+[[package]]
+name = "pysocks"
+version = "1.7.1"
+optional = true
+groups = ["main"]
+markers = "extra == \"socks\""
+files = []
+
+[metadata]
+lock-version = "2.1"
+"#;
+        let packages = import_poetry(content, None, &env(), &[]).unwrap();
+        assert_eq!(names(&packages), ["click", "colorama"]);
+        let extras = vec!["socks".to_string()];
+        let packages = import_poetry(content, None, &env(), &extras).unwrap();
+        assert_eq!(names(&packages), ["click", "colorama", "pysocks"]);
+    }
+
+    const UV_LOCK: &str = r#"
+version = 1
+requires-python = ">=3.8"
+
+[[package]]
+name = "app"
+version = "0.1.0"
+source = { editable = "." }
+dependencies = [
+    { name = "click" },
+    { name = "numpy" },
+    { name = "uvloop", marker = "sys_platform != 'win32'" },
+]
+
+[package.optional-dependencies]
+socks = [{ name = "pysocks" }]
+
+[[package]]
+name = "click"
+version = "8.1.7"
+source = { registry = "https://pypi.org/simple" }
+dependencies = [{ name = "colorama", marker = "platform_system == 'Windows'" }]
+sdist = { url = "https://example.com/click-8.1.7.tar.gz", hash = "sha256:c1", size = 1 }
+wheels = [{ url = "https://example.com/click-8.1.7-py3-none-any.whl", hash = "sha256:c2", size = 1 }]
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+source = { registry = "https://pypi.org/simple" }
+wheels = [{ url = "https://example.com/colorama-0.4.6-py2.py3-none-any.whl", hash = "sha256:d1", size = 1 }]
+
+[[package]]
+name = "numpy"
+version = "1.24.4"
+source = { registry = "https://pypi.org/simple" }
+resolution-markers = ["python_full_version < '3.9'"]
+
+[[package]]
+name = "numpy"
+version = "2.1.0"
+source = { registry = "https://pypi.org/simple" }
+resolution-markers = ["python_full_version >= '3.9'"]
+
+[[package]]
+name = "pysocks"
+version = "1.7.1"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "uvloop"
+version = "0.19.0"
+source = { registry = "https://pypi.org/simple" }
+"#;
+
+    #[test]
+    fn uv() {
+        let packages = import_uv(UV_LOCK, &env(), &[]).unwrap();
+        assert_eq!(names(&packages), ["click", "colorama", "numpy"]);
+        let numpy = packages.iter().find(|i| i.name == "numpy").unwrap();
+        assert_eq!(numpy.version, "2.1.0");
+        let click = packages.iter().find(|i| i.name == "click").unwrap();
+        assert_eq!(click.hashes, ["sha256:c2", "sha256:c1"]);
+
+        let extras = vec!["socks".to_string()];
+        let packages = import_uv(UV_LOCK, &env(), &extras).unwrap();
+        assert_eq!(names(&packages), ["click", "colorama", "numpy", "pysocks"]);
+    }
+
+    #[test]
+    fn pipfile() {
+        let content = r#"{
+    "_meta": {"hash": {"sha256": "0000"}, "pipfile-spec": 6},
+    "default": {
+        "click": {"hashes": ["sha256:aaaa"], "version": "==8.1.7"},
+        "colorama": {"hashes": ["sha256:cccc"], "markers": "platform_system == 'Windows'", "version": "==0.4.6"},
+        "uvloop": {"markers": "sys_platform != 'win32'", "version": "==0.19.0"}
+    },
+    "develop": {
+        "pytest": {"version": "==7.4.0"}
+    }
+}"#;
+        let packages = import_pipfile(content, &env()).unwrap();
+        assert_eq!(names(&packages), ["click", "colorama"]);
+        assert_eq!(packages[0].version, "8.1.7");
+        assert_eq!(packages[0].hashes, ["sha256:aaaa"]);
+
+        let git = r#"{"default": {"app": {"git": "https://example.com/app.git", "ref": "abc"}}}"#;
+        assert!(import_pipfile(git, &env()).is_err());
+    }
+
+    #[test]
+    fn poetry_python_constraint() {
+        let env = env();
+        assert!(python_matches(&env, "^3.8"));
+        assert!(!python_matches(&env, "<3.8"));
+        assert!(python_matches(&env, "~3.11"));
+        assert!(!python_matches(&env, "~3.10"));
+        assert!(python_matches(&env, "3.7 || 3.11"));
+        assert!(python_matches(&env, ">=3.7,<4.0"));
+        assert!(python_matches(&env, "*"));
+    }
+}
@@ -0,0 +1,326 @@
+// PEP 508 环境标记
+// 针对要构建的 Windows 运行时求值，而不是当前运行本程序的系统
+
+use crate::pep440::{Specifier, Version};
+use crate::Result;
+
+// 目标运行时的环境
+#[derive(Debug, Clone)]
+pub(crate) struct Environment {
+    // 完整的 Python 版本号，如 3.11.4
+    pub python: String,
+    pub is32: bool,
+}
+
+impl Environment {
+    pub fn new(python: &str, is32: bool) -> Environment {
+        Environment {
+            python: python.to_string(),
+            is32,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        let v = match name {
+            "os_name" => "nt".to_string(),
+            "sys_platform" => "win32".to_string(),
+            "platform_system" => "Windows".to_string(),
+            "platform_machine" => if self.is32 { "x86" } else { "AMD64" }.to_string(),
+            "platform_release" => "10".to_string(),
+            "platform_version" => "".to_string(),
+            "implementation_name" => "cpython".to_string(),
+            "platform_python_implementation" => "CPython".to_string(),
+            "python_full_version" | "implementation_version" => self.python.clone(),
+            "python_version" => {
+                let v: Vec<&str> = self.python.split('.').take(2).collect();
+                v.join(".")
+            }
+            _ => return None,
+        };
+        Some(v)
+    }
+
+    // 对标记表达式求值，extras 为当前要安装的 extra 名称
+    pub fn evaluate(&self, marker: &str, extras: &[String]) -> Result<bool> {
+        let tokens = tokenize(marker)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            env: self,
+            extras,
+        };
+        let result = parser.or()?;
+        if parser.pos != tokens.len() {
+            return Err(format!("无效的环境标记：{}", marker).into());
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Var(String),
+    Str(String),
+    Op(String),
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            let end = match chars[i + 1..].iter().position(|&x| x == c) {
+                Some(n) => i + 1 + n,
+                None => return Err(format!("环境标记中的字符串没有结束：{}", s).into()),
+            };
+            tokens.push(Token::Str(chars[i + 1..end].iter().collect()));
+            i = end + 1;
+        } else if "<>=!~".contains(c) {
+            let mut end = i;
+            while end < chars.len() && "<>=!~".contains(chars[end]) {
+                end += 1;
+            }
+            tokens.push(Token::Op(chars[i..end].iter().collect()));
+            i = end;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = i;
+            while end < chars.len()
+                && (chars[end].is_ascii_alphanumeric() || chars[end] == '_' || chars[end] == '.')
+            {
+                end += 1;
+            }
+            let word: String = chars[i..end].iter().collect();
+            i = end;
+            match word.as_str() {
+                "and" => tokens.push(Token::And),
+                "or" => tokens.push(Token::Or),
+                "in" => tokens.push(Token::Op("in".into())),
+                "not" => {
+                    // not in
+                    tokens.push(Token::Op("not in".into()));
+                    while i < chars.len() && chars[i].is_whitespace() {
+                        i += 1;
+                    }
+                    if chars[i..].iter().take(2).collect::<String>() != "in" {
+                        return Err(format!("无效的环境标记：{}", s).into());
+                    }
+                    i += 2;
+                }
+                // 兼容旧写法
+                "os.name" => tokens.push(Token::Var("os_name".into())),
+                "sys.platform" => tokens.push(Token::Var("sys_platform".into())),
+                "platform.version" => tokens.push(Token::Var("platform_version".into())),
+                "platform.machine" => tokens.push(Token::Var("platform_machine".into())),
+                "platform.python_implementation" => {
+                    tokens.push(Token::Var("platform_python_implementation".into()))
+                }
+                "python_implementation" => {
+                    tokens.push(Token::Var("platform_python_implementation".into()))
+                }
+                _ => tokens.push(Token::Var(word)),
+            }
+        } else {
+            return Err(format!("无效的环境标记：{}", s).into());
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    env: &'a Environment,
+    extras: &'a [String],
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        match self.tokens.get(self.pos) {
+            Some(t) => {
+                self.pos += 1;
+                Ok(t.clone())
+            }
+            None => Err("环境标记不完整".into()),
+        }
+    }
+
+    fn or(&mut self) -> Result<bool> {
+        let mut result = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            // 不能短路，需要消耗掉后面的记号
+            let rhs = self.and()?;
+            result = result || rhs;
+        }
+        Ok(result)
+    }
+
+    fn and(&mut self) -> Result<bool> {
+        let mut result = self.expr()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let rhs = self.expr()?;
+            result = result && rhs;
+        }
+        Ok(result)
+    }
+
+    fn expr(&mut self) -> Result<bool> {
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let result = self.or()?;
+            if self.next()? != Token::RParen {
+                return Err("环境标记中的括号不匹配".into());
+            }
+            return Ok(result);
+        }
+        let lhs = self.next()?;
+        let op = match self.next()? {
+            Token::Op(op) => op,
+            t => return Err(format!("环境标记中缺少比较运算符：{:?}", t).into()),
+        };
+        let rhs = self.next()?;
+        self.compare(&lhs, &op, &rhs)
+    }
+
+    fn compare(&self, lhs: &Token, op: &str, rhs: &Token) -> Result<bool> {
+        // extra 需要与所有请求的 extra 逐个比较
+        if let (Token::Var(var), Token::Str(value)) | (Token::Str(value), Token::Var(var)) =
+            (lhs, rhs)
+        {
+            if var == "extra" {
+                let value = crate::pyproject::normalize_name(value);
+                let found = self
+                    .extras
+                    .iter()
+                    .any(|i| crate::pyproject::normalize_name(i) == value);
+                return match op {
+                    "==" => Ok(found),
+                    "!=" => Ok(!found),
+                    _ => Err(format!("extra 不支持运算符 {}", op).into()),
+                };
+            }
+        }
+        let value = |t: &Token| -> Result<String> {
+            match t {
+                Token::Str(s) => Ok(s.clone()),
+                Token::Var(v) => match self.env.get(v) {
+                    Some(v) => Ok(v),
+                    None => Err(format!("未知的环境标记变量：{}", v).into()),
+                },
+                _ => Err("无效的环境标记".into()),
+            }
+        };
+        let (l, r) = (value(lhs)?, value(rhs)?);
+        match op {
+            "in" => return Ok(r.contains(&l)),
+            "not in" => return Ok(!r.contains(&l)),
+            _ => {}
+        }
+        // 两边都能解析为版本号时按 PEP 440 比较，否则按字符串比较
+        if let (Ok(v), Ok(spec)) = (
+            l.parse::<Version>(),
+            format!("{}{}", op, r).parse::<Specifier>(),
+        ) {
+            return Ok(spec.contains(&v));
+        }
+        match op {
+            "==" | "===" => Ok(l == r),
+            "!=" => Ok(l != r),
+            "<" => Ok(l < r),
+            "<=" => Ok(l <= r),
+            ">" => Ok(l > r),
+            ">=" => Ok(l >= r),
+            _ => Err(format!("无法比较 {} {} {}", l, op, r).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(marker: &str) -> bool {
+        Environment::new("3.11.7", false)
+            .evaluate(marker, &[])
+            .unwrap()
+    }
+
+    #[test]
+    fn and_or() {
+        assert!(eval(
+            r#"sys_platform == "win32" and python_version >= "3.8""#
+        ));
+        assert!(!eval(
+            r#"sys_platform == "linux" and python_version >= "3.8""#
+        ));
+        assert!(eval(r#"sys_platform == "linux" or os_name == "nt""#));
+        // and 的优先级高于 or
+        assert!(eval(
+            r#"os_name == "posix" and python_version < "3" or platform_system == "Windows""#
+        ));
+        assert!(!eval(
+            r#"os_name == "posix" and (python_version < "3" or platform_system == "Windows")"#
+        ));
+    }
+
+    #[test]
+    fn version_and_string_compare() {
+        // 按版本比较时 3.11 大于 3.9，按字符串比较则相反
+        assert!(eval(r#"python_version > "3.9""#));
+        assert!(eval(r#"python_full_version >= "3.11.0""#));
+        assert!(eval(r#"python_version ~= "3.10""#));
+        assert!(eval(r#"python_version == "3.11.*""#));
+        assert!(!eval(r#"python_version < "3.8""#));
+        assert!(eval(r#"platform_machine == "AMD64""#));
+        assert!(eval(r#""win" in sys_platform"#));
+        assert!(eval(
+            r#"platform_python_implementation not in "PyPy Jython""#
+        ));
+        assert!(eval(r#"'3.11' == python_version"#));
+        // 旧写法
+        assert!(eval(r#"sys.platform == "win32""#));
+    }
+
+    #[test]
+    fn extra() {
+        let env = Environment::new("3.11.7", true);
+        let extras = vec!["Socks".to_string()];
+        assert!(env.evaluate(r#"extra == "socks""#, &extras).unwrap());
+        assert!(!env.evaluate(r#"extra == "security""#, &extras).unwrap());
+        assert!(!env.evaluate(r#"extra == "socks""#, &[]).unwrap());
+        assert!(env
+            .evaluate(
+                r#"platform_machine == "x86" and extra != "security""#,
+                &extras
+            )
+            .unwrap());
+    }
+
+    #[test]
+    fn invalid() {
+        let env = Environment::new("3.11.7", false);
+        assert!(env.evaluate(r#"python_version >= "3.8"#, &[]).is_err());
+        assert!(env.evaluate(r#"unknown_var == "1""#, &[]).is_err());
+        assert!(env.evaluate(r#"(python_version >= "3.8""#, &[]).is_err());
+        assert!(env.evaluate(r#"python_version >= "3.8" and"#, &[]).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+// 解析 METADATA 文件（Core metadata），以及查找已安装的 dist-info 目录
+
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Metadata {
+    pub name: String,
+    pub version: String,
+    pub requires_dist: Vec<String>,
+    pub requires_python: Option<String>,
+    pub license: Option<String>,
+    pub classifiers: Vec<String>,
+}
+
+// METADATA 是 email 头格式，空行之后是长描述，这里不需要
+pub(crate) fn parse(text: &str) -> Metadata {
+    let mut result = Metadata::default();
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in text.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            // 续行
+            if let Some(last) = headers.last_mut() {
+                last.1.push('\n');
+                last.1.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.push((k.trim().to_ascii_lowercase(), v.trim().to_string()));
+        }
+    }
+    for (k, v) in headers {
+        match k.as_str() {
+            "name" => result.name = v,
+            "version" => result.version = v,
+            "requires-dist" => result.requires_dist.push(v),
+            "requires-python" => result.requires_python = Some(v),
+            "license-expression" => result.license = Some(v),
+            "license" if result.license.is_none() && !v.is_empty() => {
+                // 有些包把整个许可证文本放在这里，只取第一行
+                result.license = v.lines().next().map(|i| i.to_string())
+            }
+            "classifier" => result.classifiers.push(v),
+            _ => {}
+        }
+    }
+    result
+}
+
+// 只保留头部，去掉正文中的长描述以及旧格式放在 Description 字段中的长描述
+pub(crate) fn trim(text: &str) -> String {
+    let mut result = String::new();
+    let mut skip = false;
+    for line in text.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            // 续行
+            if !skip {
+                result += line;
+                result.push('\n');
+            }
+            continue;
+        }
+        skip = line.to_ascii_lowercase().starts_with("description:");
+        if !skip {
+            result += line;
+            result.push('\n');
+        }
+    }
+    result
+}
+
+// site-packages 中所有 dist-info 目录
+pub(crate) fn dist_infos(site: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut result = vec![];
+    if !site.exists() {
+        return Ok(result);
+    }
+    for i in std::fs::read_dir(site)? {
+        let i = i?;
+        let path = i.path();
+        if path.is_dir() && i.file_name().to_string_lossy().ends_with(".dist-info") {
+            result.push(path);
+        }
+    }
+    result.sort();
+    Ok(result)
+}
+
+pub(crate) fn read(dist_info: &std::path::Path) -> Result<Metadata> {
+    let path = dist_info.join("METADATA");
+    if !path.exists() {
+        return Err(format!("{} 不存在", path.display()).into());
+    }
+    Ok(parse(&String::from_utf8_lossy(&std::fs::read(path)?)))
+}
+
+// 所有已安装的包
+pub(crate) fn installed(site: &std::path::Path) -> Result<Vec<(std::path::PathBuf, Metadata)>> {
+    let mut result = vec![];
+    for i in dist_infos(site)? {
+        let metadata = read(&i)?;
+        result.push((i, metadata));
+    }
+    Ok(result)
+}
+
+// 按名称查找已安装的包
+pub(crate) fn find(
+    site: &std::path::Path,
+    name: &str,
+) -> Result<Option<(std::path::PathBuf, Metadata)>> {
+    let name = normalize_name(name);
+    Ok(installed(site)?
+        .into_iter()
+        .find(|(_, m)| normalize_name(&m.name) == name))
+}
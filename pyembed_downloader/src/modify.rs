@@ -0,0 +1,274 @@
+// 修改已经构建好的运行时：添加、删除、升级依赖包
+// 不重新安装 pip，只编译新安装的文件，需要构建时保留 dist-info 才能知道已安装了哪些包
+// 指定了 output_dir 时修改的是完整的构建目录，之后重新导出
+
+use crate::config::{Backend, Config};
+use crate::metadata::Metadata;
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Add,
+    Remove,
+    Upgrade,
+}
+
+type Snapshot = std::collections::BTreeMap<String, (std::path::PathBuf, Metadata)>;
+
+// RECORD 的修改时间和内容，重新安装时 RECORD 会重新生成
+type RecordStamp = (Option<std::time::SystemTime>, Vec<u8>);
+
+pub(crate) async fn modify(
+    config: &Config,
+    operation: Operation,
+    packages: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<()> {
+    let _job = crate::utility::setup_job()?;
+
+    let targetdir = std::env::current_dir()?.join(&config.dir);
+    let cachedir = std::env::current_dir()?.join(&config.cache_dir);
+    let site = targetdir.join("Lib").join("site-packages");
+    if packages.is_empty() {
+        return Err("没有指定依赖包".into());
+    }
+    crate::features::check(config)?;
+    if !targetdir.join("python.exe").exists() {
+        return Err(format!("{} 中没有运行时，请先构建", targetdir.display()).into());
+    }
+    std::fs::create_dir_all(&cachedir)?;
+    let outdir = crate::output_dir(config, &targetdir)?;
+    if outdir.is_none()
+        && targetdir
+            .join("Lib")
+            .join(crate::zipsite::ZIP_NAME)
+            .exists()
+    {
+        return Err("运行时的 site-packages 已打包为 zip，无法修改，请重新构建".into());
+    }
+
+    let before = snapshot(&site)?;
+    if before.is_empty() && !crate::is_empty_dir(&site)? {
+        return Err(
+            "运行时中的 dist-info 已被删除，无法确定已安装的包，请使用 --keep-dist-info 重新构建"
+                .into(),
+        );
+    }
+    if before.values().any(|(i, _)| !i.join("RECORD").exists()) {
+        return Err(
+            "运行时中的 dist-info 只保留了最小元数据，无法卸载已安装的包，请使用 --keep-dist-info 重新构建"
+                .into(),
+        );
+    }
+    let mut stamps = std::collections::BTreeMap::new();
+    for (name, (dist_info, _)) in before.iter() {
+        stamps.insert(name.clone(), record_stamp(dist_info)?);
+    }
+    let v = crate::get_local_python_version(&targetdir, config.installer != Backend::Pip)?;
+    let pyver = format!("{}.{}.{}", v.0, v.1, v.2);
+    info!("Python 版本：{}", pyver);
+    if config.installer == Backend::Pip && !before.contains_key("pip") {
+        return Err(
+            "运行时中没有 pip，请使用 --installer uv 或 native，或使用 --keep-pip 重新构建".into(),
+        );
+    }
+    let index_args = crate::pip_index_args(config)?;
+    let installer = crate::new_installer(config, &targetdir, &pyver, &index_args)?;
+
+    if operation == Operation::Remove {
+        let names: Vec<String> = packages.iter().map(|i| normalize_name(i)).collect();
+        for i in names.iter() {
+            if !before.contains_key(i) {
+                return Err(format!("{} 没有安装", i).into());
+            }
+        }
+        warn!("卸载依赖包 ...");
+        let names: Vec<&str> = names.iter().map(|i| i.as_str()).collect();
+        match &installer {
+            Some(installer) => installer.uninstall(&names)?,
+            None => {
+                for i in names {
+                    crate::wheel::uninstall(&targetdir, i)?;
+                }
+            }
+        }
+    } else {
+        // 本地包与构建时一样处理
+        let mut local = config.clone();
+        local.packages = packages.to_vec();
+        local.project = None;
+        let build = |source: &std::path::Path| {
+            let cmd = if config.installer == Backend::Pip && cfg!(windows) {
+                crate::new_python_command(&targetdir)
+            } else {
+                crate::new_host_python_command(config)
+            };
+            crate::build_wheel(config, cmd, &cachedir, source, &index_args)
+        };
+        let (specs, _, _) = crate::collect_packages(&local, true, &pyver, &build)?;
+        let mut packages = vec![];
+        let mut names = vec![];
+        for i in specs {
+            let name = match crate::lock::requirement_name(&i) {
+                Some(name) => name,
+                None => return Err(format!("无效的依赖声明：{}", i).into()),
+            };
+            let installed = before.contains_key(&name);
+            if operation == Operation::Upgrade && !installed {
+                return Err(format!("{} 没有安装，请使用 add 添加", name).into());
+            }
+            if operation == Operation::Add && installed {
+                info!("已安装，跳过：{}", name);
+                continue;
+            }
+            packages.push(i);
+            names.push(name);
+        }
+        if packages.is_empty() {
+            warn!("完成！");
+            return Ok(());
+        }
+
+        warn!("安装依赖包 ...");
+        match &installer {
+            Some(installer) => installer.install(&packages, None, None)?,
+            None => {
+                // 只解析新的依赖，已安装的包除了要升级的都保持不变
+                let mut finder = crate::new_finder(config, &pyver, &cachedir)?;
+                let env = crate::markers::Environment::new(&pyver, config.is32);
+                let skip: Vec<String> = before
+                    .keys()
+                    .filter(|i| !names.contains(i))
+                    .cloned()
+                    .collect();
+                let plan = crate::resolver::resolve(
+                    &mut finder,
+                    &packages,
+                    &env,
+                    &skip,
+                    progress_callback,
+                )
+                .await?;
+                for (candidate, requested) in plan {
+                    if let Some((_, old)) = before.get(&candidate.name) {
+                        if old.version == candidate.version.to_string() {
+                            info!("已是最新版本：{} {}", old.name, old.version);
+                            continue;
+                        }
+                    }
+                    let path = finder.fetch(&candidate, progress_callback).await?;
+                    crate::wheel::install(&path, &targetdir, requested)?;
+                }
+            }
+        }
+    }
+
+    let after = snapshot(&site)?;
+    info!("变更");
+    let mut changed = vec![];
+    for (name, (_, old)) in before.iter() {
+        if !after.contains_key(name) {
+            info!("删除：{} {}", old.name, old.version);
+        }
+    }
+    for (name, (dist_info, new)) in after.iter() {
+        match before.get(name) {
+            None => info!("新增：{} {}", new.name, new.version),
+            Some((_, old)) if old.version != new.version => {
+                info!("更新：{} {} -> {}", new.name, old.version, new.version)
+            }
+            // 版本相同且 RECORD 没有变化，说明没有重新安装
+            // 不能按是否有 .py 判断，按规则保留源文件的包总是有 .py
+            Some(_) if stamps.get(name) == Some(&record_stamp(dist_info)?) => continue,
+            Some(_) => info!("重新安装：{} {}", new.name, new.version),
+        }
+        changed.push(dist_info.clone());
+    }
+
+    if let Some(outdir) = &outdir {
+        // 构建时 keep_pip 或明确要求了 pip 等包时，上次导出的运行时中保留了 pip
+        let exported_pip = outdir
+            .join("Lib")
+            .join("site-packages")
+            .join("pip")
+            .is_dir();
+        let keeppip = exported_pip
+            || (operation != Operation::Remove && crate::keep_pip(config, packages))
+            || config.keep_pip;
+        crate::check_runtime(config, &targetdir, &pyver, keeppip)?;
+        if config.recompile_stdlib {
+            crate::stdlib::download_source(config, &cachedir, &pyver, progress_callback).await?;
+        }
+        let tracker = crate::size::Tracker::new(&targetdir, &pyver)?;
+        crate::export(&targetdir, outdir)?;
+        crate::strip_runtime(config, outdir, &pyver, keeppip, None, progress_callback)?;
+        crate::check_max_size(config, &tracker.finish(outdir, &pyver)?)?;
+        warn!("完成！");
+        return Ok(());
+    }
+    if !changed.is_empty() {
+        warn!("正在编译 ...");
+        let mut paths = vec![];
+        for i in changed.iter() {
+            for path in top_level(i)? {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        crate::normalize_mtime(config, &targetdir)?;
+        crate::compile_runtime(config, &targetdir, &pyver, &paths, progress_callback)?;
+        // 只处理新安装的包，已有的包在构建时已经处理过
+        if config.strip || config.strip_rules.is_some() {
+            warn!("正在删除不需要的文件 ...");
+            crate::strip::strip(config, &targetdir, &pyver, &paths)?;
+        }
+    }
+    if !config.keep_scripts {
+        let scripts = targetdir.join("Scripts");
+        if scripts.exists() {
+            info!("删除目录：{}", scripts.display());
+            std::fs::remove_dir_all(scripts)?;
+        }
+    }
+    crate::check_runtime(config, &targetdir, &pyver, true)?;
+    crate::normalize_mtime(config, &targetdir)?;
+
+    warn!("完成！");
+    Ok(())
+}
+
+fn snapshot(site: &std::path::Path) -> Result<Snapshot> {
+    Ok(crate::metadata::installed(site)?
+        .into_iter()
+        .map(|(dist_info, metadata)| (normalize_name(&metadata.name), (dist_info, metadata)))
+        .collect())
+}
+
+// RECORD 中位于 site-packages 的顶层文件和目录，不包括 dist-info 和 Scripts 等外部文件
+pub(crate) fn top_level(dist_info: &std::path::Path) -> Result<Vec<String>> {
+    let mut result = vec![];
+    let content = match std::fs::read_to_string(dist_info.join("RECORD")) {
+        Ok(content) => content,
+        Err(_) => return Ok(result),
+    };
+    for line in content.lines() {
+        let path = line.split(',').next().unwrap_or_default().trim_matches('"');
+        let first = path.split('/').next().unwrap_or_default();
+        if first.is_empty() || first == ".." || first.ends_with(".dist-info") {
+            continue;
+        }
+        let first = first.to_string();
+        if !result.contains(&first) {
+            result.push(first);
+        }
+    }
+    Ok(result)
+}
+
+fn record_stamp(dist_info: &std::path::Path) -> Result<RecordStamp> {
+    let path = dist_info.join("RECORD");
+    let modified = std::fs::metadata(&path)?.modified().ok();
+    Ok((modified, std::fs::read(&path)?))
+}
@@ -0,0 +1,523 @@
+// PEP 440 版本号与版本约束
+// 只实现了比较和匹配所需的部分，不做完整的规范化输出
+
+use crate::Result;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreKind, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    pub local: Vec<LocalSegment>,
+}
+
+// (epoch, release, pre, post, dev, local)
+type VersionKey<'a> = (
+    u64,
+    Vec<u64>,
+    (i8, u64),
+    (i8, u64),
+    (i8, u64),
+    &'a [LocalSegment],
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PreKind {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum LocalSegment {
+    // 字母段排在数字段之前
+    Str(String),
+    Num(u64),
+}
+
+fn version_regex() -> &'static regex::Regex {
+    static RE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    RE.get_or_init(|| {
+        regex::RegexBuilder::new(
+            r"^\s*v?(?:(?P<epoch>[0-9]+)!)?(?P<release>[0-9]+(?:\.[0-9]+)*)(?P<pre>[-_\.]?(?P<pre_l>alpha|a|beta|b|preview|pre|c|rc)[-_\.]?(?P<pre_n>[0-9]+)?)?(?P<post>(?:-(?P<post_n1>[0-9]+))|(?:[-_\.]?(?P<post_l>post|rev|r)[-_\.]?(?P<post_n2>[0-9]+)?))?(?P<dev>[-_\.]?dev[-_\.]?(?P<dev_n>[0-9]+)?)?(?:\+(?P<local>[a-z0-9]+(?:[-_\.][a-z0-9]+)*))?\s*$",
+        )
+        .case_insensitive(true)
+        .build()
+        .unwrap()
+    })
+}
+
+impl std::str::FromStr for Version {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Version> {
+        let caps = match version_regex().captures(s) {
+            Some(caps) => caps,
+            None => return Err(format!("无效的版本号：{}", s).into()),
+        };
+        let num = |name: &str| -> Result<Option<u64>> {
+            match caps.name(name) {
+                Some(m) => Ok(Some(m.as_str().parse()?)),
+                None => Ok(None),
+            }
+        };
+        let mut release = vec![];
+        for i in caps["release"].split('.') {
+            release.push(i.parse()?);
+        }
+        let pre = match caps.name("pre_l") {
+            Some(l) => {
+                let kind = match l.as_str().to_ascii_lowercase().as_str() {
+                    "a" | "alpha" => PreKind::Alpha,
+                    "b" | "beta" => PreKind::Beta,
+                    _ => PreKind::Rc,
+                };
+                Some((kind, num("pre_n")?.unwrap_or(0)))
+            }
+            None => None,
+        };
+        let post = if caps.name("post").is_some() {
+            Some(num("post_n1")?.or(num("post_n2")?).unwrap_or(0))
+        } else {
+            None
+        };
+        let dev = if caps.name("dev").is_some() {
+            Some(num("dev_n")?.unwrap_or(0))
+        } else {
+            None
+        };
+        let local = match caps.name("local") {
+            Some(m) => m
+                .as_str()
+                .split(['-', '_', '.'])
+                .map(|i| match i.parse() {
+                    Ok(n) => LocalSegment::Num(n),
+                    Err(_) => LocalSegment::Str(i.to_ascii_lowercase()),
+                })
+                .collect(),
+            None => vec![],
+        };
+        Ok(Version {
+            epoch: num("epoch")?.unwrap_or(0),
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<String> = self.release.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}", release.join("."))?;
+        if let Some((kind, n)) = self.pre {
+            let kind = match kind {
+                PreKind::Alpha => "a",
+                PreKind::Beta => "b",
+                PreKind::Rc => "rc",
+            };
+            write!(f, "{}{}", kind, n)?;
+        }
+        if let Some(n) = self.post {
+            write!(f, ".post{}", n)?;
+        }
+        if let Some(n) = self.dev {
+            write!(f, ".dev{}", n)?;
+        }
+        if !self.local.is_empty() {
+            let local: Vec<String> = self
+                .local
+                .iter()
+                .map(|i| match i {
+                    LocalSegment::Str(s) => s.clone(),
+                    LocalSegment::Num(n) => n.to_string(),
+                })
+                .collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+impl Version {
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    // 去掉 local 部分，用于与不含 local 的约束比较
+    fn public(&self) -> Version {
+        Version {
+            local: vec![],
+            ..self.clone()
+        }
+    }
+
+    fn base(&self) -> Version {
+        Version {
+            epoch: self.epoch,
+            release: self.release.clone(),
+            pre: None,
+            post: None,
+            dev: None,
+            local: vec![],
+        }
+    }
+
+    // 按 PEP 440 规定的顺序生成比较键
+    // pre：只有 dev 时排在所有预发布版本之前，没有预发布时排在最后
+    // post：没有时排在最前；dev：没有时排在最后
+    fn key(&self) -> VersionKey<'_> {
+        let mut release = self.release.clone();
+        while release.len() > 1 && *release.last().unwrap() == 0 {
+            release.pop();
+        }
+        let pre = match self.pre {
+            Some((kind, n)) => (kind as i8, n),
+            None if self.post.is_none() && self.dev.is_some() => (-1, 0),
+            None => (i8::MAX, 0),
+        };
+        let post = match self.post {
+            Some(n) => (0, n),
+            None => (-1, 0),
+        };
+        let dev = match self.dev {
+            Some(n) => (0, n),
+            None => (1, 0),
+        };
+        (self.epoch, release, pre, post, dev, &self.local)
+    }
+}
+
+// 与 cmp 一致，release 末尾的 0 不影响相等，如 1.0 == 1.0.0
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operator {
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+    Compatible,
+    Arbitrary,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Specifier {
+    pub op: Operator,
+    pub version: String,
+    // ==1.2.* 和 !=1.2.* 形式的通配
+    wildcard: bool,
+}
+
+impl std::str::FromStr for Specifier {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Specifier> {
+        let s = s.trim();
+        let (op, rest) = if let Some(rest) = s.strip_prefix("===") {
+            (Operator::Arbitrary, rest)
+        } else if let Some(rest) = s.strip_prefix("==") {
+            (Operator::Equal, rest)
+        } else if let Some(rest) = s.strip_prefix("!=") {
+            (Operator::NotEqual, rest)
+        } else if let Some(rest) = s.strip_prefix("<=") {
+            (Operator::LessEqual, rest)
+        } else if let Some(rest) = s.strip_prefix(">=") {
+            (Operator::GreaterEqual, rest)
+        } else if let Some(rest) = s.strip_prefix("~=") {
+            (Operator::Compatible, rest)
+        } else if let Some(rest) = s.strip_prefix('<') {
+            (Operator::Less, rest)
+        } else if let Some(rest) = s.strip_prefix('>') {
+            (Operator::Greater, rest)
+        } else {
+            return Err(format!("无效的版本约束：{}", s).into());
+        };
+        let rest = rest.trim();
+        let (version, wildcard) = match rest.strip_suffix(".*") {
+            Some(v) if matches!(op, Operator::Equal | Operator::NotEqual) => (v, true),
+            _ => (rest, false),
+        };
+        if op != Operator::Arbitrary {
+            version.parse::<Version>()?;
+        }
+        Ok(Specifier {
+            op,
+            version: version.to_string(),
+            wildcard,
+        })
+    }
+}
+
+impl std::fmt::Display for Specifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self.op {
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::LessEqual => "<=",
+            Operator::GreaterEqual => ">=",
+            Operator::Less => "<",
+            Operator::Greater => ">",
+            Operator::Compatible => "~=",
+            Operator::Arbitrary => "===",
+        };
+        write!(f, "{}{}", op, self.version)?;
+        if self.wildcard {
+            write!(f, ".*")?;
+        }
+        Ok(())
+    }
+}
+
+impl Specifier {
+    pub fn contains(&self, v: &Version) -> bool {
+        if self.op == Operator::Arbitrary {
+            return v.to_string().eq_ignore_ascii_case(&self.version);
+        }
+        let spec: Version = match self.version.parse() {
+            Ok(spec) => spec,
+            Err(_) => return false,
+        };
+        match self.op {
+            Operator::Equal | Operator::NotEqual => {
+                let eq = if self.wildcard {
+                    prefix_match(&spec, v)
+                } else if spec.local.is_empty() {
+                    v.public() == spec
+                } else {
+                    *v == spec
+                };
+                eq == (self.op == Operator::Equal)
+            }
+            Operator::LessEqual => v.public() <= spec,
+            Operator::GreaterEqual => v.public() >= spec,
+            Operator::Less => {
+                // <V 不包含 V 的预发布版本，除非 V 本身就是预发布版本
+                v.public() < spec
+                    && (spec.is_prerelease()
+                        || !v.is_prerelease()
+                        || v.base().cmp(&spec.base()) != std::cmp::Ordering::Equal)
+            }
+            Operator::Greater => {
+                // >V 不包含 V 的后发布版本和 local 版本，除非 V 本身就是后发布版本
+                v.public() > spec
+                    && (spec.post.is_some()
+                        || v.post.is_none()
+                        || v.base().cmp(&spec.base()) != std::cmp::Ordering::Equal)
+            }
+            Operator::Compatible => {
+                if spec.release.len() < 2 {
+                    return false;
+                }
+                let mut prefix = spec.base();
+                prefix.release.pop();
+                v.public() >= spec && prefix_match(&prefix, v)
+            }
+            Operator::Arbitrary => unreachable!(),
+        }
+    }
+}
+
+fn prefix_match(prefix: &Version, v: &Version) -> bool {
+    if prefix.epoch != v.epoch {
+        return false;
+    }
+    let mut release = v.release.clone();
+    while release.len() < prefix.release.len() {
+        release.push(0);
+    }
+    release[..prefix.release.len()] == prefix.release[..]
+}
+
+// 以逗号分隔的一组约束，全部满足才算匹配
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Specifiers(pub Vec<Specifier>);
+
+impl std::str::FromStr for Specifiers {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Specifiers> {
+        let mut result = vec![];
+        for i in s.split(',') {
+            if !i.trim().is_empty() {
+                result.push(i.parse()?);
+            }
+        }
+        Ok(Specifiers(result))
+    }
+}
+
+impl std::fmt::Display for Specifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let specs: Vec<String> = self.0.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}", specs.join(","))
+    }
+}
+
+impl Specifiers {
+    pub fn contains(&self, v: &Version) -> bool {
+        self.0.iter().all(|i| i.contains(v))
+    }
+
+    // 约束中明确写了预发布版本时允许使用预发布版本
+    pub fn allows_prerelease(&self) -> bool {
+        self.0.iter().any(|i| {
+            i.op != Operator::NotEqual
+                && i.version
+                    .parse::<Version>()
+                    .is_ok_and(|v| v.is_prerelease())
+        })
+    }
+
+    // 是否精确指定了版本，如 ==1.2.3
+    pub fn is_exact(&self) -> bool {
+        self.0
+            .iter()
+            .any(|i| matches!(i.op, Operator::Equal | Operator::Arbitrary) && !i.wildcard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    fn spec(s: &str) -> Specifiers {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn ordering() {
+        // PEP 440 中给出的顺序
+        let versions = [
+            "1.0.dev456",
+            "1.0a1",
+            "1.0a2.dev456",
+            "1.0a12.dev456",
+            "1.0a12",
+            "1.0b1.dev456",
+            "1.0b2",
+            "1.0b2.post345.dev456",
+            "1.0b2.post345",
+            "1.0rc1.dev456",
+            "1.0rc1",
+            "1.0",
+            "1.0+abc.5",
+            "1.0+abc.7",
+            "1.0+5",
+            "1.0.post456.dev34",
+            "1.0.post456",
+            "1.1.dev1",
+            "1!0.1",
+        ];
+        for pair in versions.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!(v("1.0"), v("1.0"));
+        assert_eq!(v("1.0").cmp(&v("1.0.0")), std::cmp::Ordering::Equal);
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert_ne!(v("1.0"), v("1.0.1"));
+        assert!(spec("==1.0").contains(&v("1.0.0")));
+        assert!(spec("==1.0.0").contains(&v("1.0")));
+        assert!(!spec("!=1.0").contains(&v("1.0.0")));
+        assert!(spec("!=1.0").contains(&v("1.0.1")));
+        assert_eq!(v("1.0-alpha.1").to_string(), "1.0a1");
+        assert_eq!(v("1.0-1").to_string(), "1.0.post1");
+        assert_eq!(v("v1.0RC2").to_string(), "1.0rc2");
+        assert_eq!(v("1.0+Ubuntu-1").to_string(), "1.0+ubuntu.1");
+        assert!(v("1.0.dev0").is_prerelease());
+        assert!(!v("1.0.post1").is_prerelease());
+        assert!("1.0-foo".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn compatible() {
+        let s = spec("~=2.2");
+        assert!(s.contains(&v("2.2")));
+        assert!(s.contains(&v("2.9.1")));
+        assert!(!s.contains(&v("3.0")));
+        assert!(!s.contains(&v("2.1")));
+        let s = spec("~=1.4.5");
+        assert!(s.contains(&v("1.4.9")));
+        assert!(!s.contains(&v("1.5.0")));
+        // 只有一段时无效
+        assert!(!spec("~=1").contains(&v("1.0")));
+    }
+
+    #[test]
+    fn wildcard() {
+        let s = spec("==1.1.*");
+        assert!(s.contains(&v("1.1")));
+        assert!(s.contains(&v("1.1.post1")));
+        assert!(s.contains(&v("1.1.5")));
+        assert!(!s.contains(&v("1.10")));
+        let s = spec("!=1.1.*");
+        assert!(!s.contains(&v("1.1.3")));
+        assert!(s.contains(&v("1.2")));
+        assert!(spec(">=1.0,!=1.3.*,<2").contains(&v("1.4")));
+        assert!(!spec(">=1.0,!=1.3.*,<2").contains(&v("1.3.1")));
+    }
+
+    #[test]
+    fn arbitrary_and_local() {
+        assert!(spec("===1.0+local").contains(&v("1.0+local")));
+        assert!(!spec("===1.0").contains(&v("1.0.0")));
+        // 约束不含 local 时忽略版本的 local 部分
+        assert!(spec("==1.0").contains(&v("1.0+cpu")));
+        assert!(!spec("==1.0+cu118").contains(&v("1.0+cpu")));
+        assert!(spec("<=1.0").contains(&v("1.0+cpu")));
+    }
+
+    #[test]
+    fn exclusive_ordered() {
+        // <V 不包含 V 的预发布版本，>V 不包含 V 的后发布版本
+        assert!(!spec("<1.0").contains(&v("1.0a1")));
+        assert!(spec("<1.0rc1").contains(&v("1.0a1")));
+        assert!(!spec(">1.0").contains(&v("1.0.post1")));
+        assert!(spec(">1.0.post1").contains(&v("1.0.post2")));
+        assert!(spec(">1.0").contains(&v("1.1")));
+    }
+
+    #[test]
+    fn prerelease_and_exact() {
+        assert!(spec(">=1.0a1").allows_prerelease());
+        assert!(!spec(">=1.0,!=1.1a1").allows_prerelease());
+        assert!(spec("==1.2.3").is_exact());
+        assert!(!spec("==1.2.*").is_exact());
+        assert!(!spec(">=1.2.3").is_exact());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::Result;
+
+// pyproject.toml 中 [project] 表的依赖信息
+pub(crate) struct Project {
+    // pyproject.toml 所在的目录
+    pub dir: std::path::PathBuf,
+    pub name: String,
+    // [build-system].requires，离线构建项目时需要
+    pub build_requires: Vec<String>,
+    dependencies: Option<Vec<String>>,
+    optional_dependencies: std::collections::BTreeMap<String, Vec<String>>,
+}
+
+impl Project {
+    // path 可以是项目目录，也可以直接是 pyproject.toml 文件
+    pub fn load(path: &std::path::Path) -> Result<Project> {
+        let file = if path.is_dir() {
+            path.join("pyproject.toml")
+        } else {
+            path.to_path_buf()
+        };
+        if !file.exists() {
+            return Err(format!("{} 不存在", file.display()).into());
+        }
+        let dir = file.parent().unwrap().to_path_buf();
+        let content = std::fs::read_to_string(&file)?;
+        let doc: toml::Table = content.parse()?;
+        let build_requires = string_array(
+            doc.get("build-system").and_then(|i| i.get("requires")),
+            "build-system.requires",
+        )?;
+        let project = match doc.get("project").and_then(|i| i.as_table()) {
+            Some(project) => project,
+            None => return Err("pyproject.toml 中没有 [project] 表".into()),
+        };
+        let name = project
+            .get("name")
+            .and_then(|i| i.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let dynamic: Vec<&str> = project
+            .get("dynamic")
+            .and_then(|i| i.as_array())
+            .map(|i| i.iter().filter_map(|i| i.as_str()).collect())
+            .unwrap_or_default();
+
+        // 声明为 dynamic 的依赖只能在构建时才能得到，这里记为 None
+        let dependencies = if dynamic.contains(&"dependencies") {
+            None
+        } else {
+            Some(string_array(project.get("dependencies"), "dependencies")?)
+        };
+        let mut optional_dependencies = std::collections::BTreeMap::new();
+        if let Some(table) = project
+            .get("optional-dependencies")
+            .and_then(|i| i.as_table())
+        {
+            for (k, v) in table {
+                optional_dependencies.insert(
+                    normalize_name(k),
+                    string_array(Some(v), &format!("optional-dependencies.{}", k))?,
+                );
+            }
+        }
+
+        Ok(Project {
+            dir,
+            name,
+            build_requires,
+            dependencies,
+            optional_dependencies,
+        })
+    }
+
+    // 依赖是否声明为 dynamic，此时只能通过安装项目本身来安装依赖
+    pub fn is_dynamic(&self) -> bool {
+        self.dependencies.is_none()
+    }
+
+    // 返回 [project].dependencies 以及指定 extras 的依赖列表
+    // 形如 myapp[extra] 的自引用会被展开为对应的 extra
+    pub fn dependencies(&self, extras: &[String]) -> Result<Vec<String>> {
+        let mut result = vec![];
+        let mut visited = vec![];
+        if let Some(deps) = &self.dependencies {
+            for i in deps {
+                self.expand(i, &mut visited, &mut result)?;
+            }
+        }
+        for extra in extras {
+            self.expand_extra(extra, &mut visited, &mut result)?;
+        }
+        Ok(result)
+    }
+
+    fn expand_extra(
+        &self,
+        extra: &str,
+        visited: &mut Vec<String>,
+        result: &mut Vec<String>,
+    ) -> Result<()> {
+        let extra = normalize_name(extra);
+        if visited.contains(&extra) {
+            return Ok(());
+        }
+        let deps = match self.optional_dependencies.get(&extra) {
+            Some(deps) => deps,
+            None => return Err(format!("项目中没有名为 {} 的 extra", extra).into()),
+        };
+        visited.push(extra);
+        for i in deps {
+            self.expand(i, visited, result)?;
+        }
+        Ok(())
+    }
+
+    fn expand(&self, req: &str, visited: &mut Vec<String>, result: &mut Vec<String>) -> Result<()> {
+        if let Some(caps) = crate::utility::regex_find(
+            r"^\s*([A-Za-z0-9][A-Za-z0-9._-]*)\s*\[([^\]]*)\]\s*(;.*)?$",
+            req,
+        ) {
+            let name = caps.get(1).unwrap().as_str();
+            // 带环境标记的自引用无法在这里判断，仍交给 pip 处理
+            if caps.get(3).is_none()
+                && !self.name.is_empty()
+                && normalize_name(name) == normalize_name(&self.name)
+            {
+                for extra in caps.get(2).unwrap().as_str().split(',') {
+                    let extra = extra.trim();
+                    if !extra.is_empty() {
+                        self.expand_extra(extra, visited, result)?;
+                    }
+                }
+                return Ok(());
+            }
+        }
+        if !result.iter().any(|i| i == req) {
+            result.push(req.to_string());
+        }
+        Ok(())
+    }
+}
+
+fn string_array(value: Option<&toml::Value>, key: &str) -> Result<Vec<String>> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(vec![]),
+    };
+    let mut result = vec![];
+    if let Some(array) = value.as_array() {
+        for i in array {
+            match i.as_str() {
+                Some(s) => result.push(s.to_string()),
+                None => return Err(format!("{} 中含有非字符串的值", key).into()),
+            }
+        }
+        return Ok(result);
+    }
+    Err(format!("{} 不是数组", key).into())
+}
+
+// PEP 503 名称规范化
+pub(crate) fn normalize_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut last_sep = false;
+    for c in name.trim().chars() {
+        if c == '-' || c == '_' || c == '.' {
+            if !last_sep {
+                result.push('-');
+            }
+            last_sep = true;
+        } else {
+            result.push(c.to_ascii_lowercase());
+            last_sep = false;
+        }
+    }
+    result
+}
@@ -0,0 +1,144 @@
+// 安装结果，从 site-packages 中的 dist-info 读取，来源 URL 取自安装报告和锁文件
+
+use crate::lock::LockedPackage;
+use crate::pyproject::normalize_name;
+use crate::size::SizeReport;
+use crate::Result;
+
+// run 的构建结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BuildReport {
+    pub installed: Vec<InstalledDistribution>,
+    // 编译失败的文件，这些文件保留了源文件
+    pub compile_failures: Vec<CompileFailure>,
+    // 编译和清理前后的磁盘占用
+    pub size: SizeReport,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CompileFailure {
+    // 相对 site-packages 的路径
+    pub file: String,
+    // 编译时的异常信息
+    pub error: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct InstalledDistribution {
+    pub name: String,
+    pub version: String,
+    // 是否为直接要求安装的包，否则是作为依赖安装的
+    pub requested: bool,
+    // 下载地址，本地文件为 file:// URL，不知道来源时为 None
+    pub url: Option<String>,
+    // WHEEL 文件中的标签，如 cp311-cp311-win_amd64
+    pub tags: Vec<String>,
+    pub license: Option<String>,
+}
+
+// requested 为规范化后的包名，reports 为 pip install --report 格式的安装报告
+// 没有安装报告时（按锁文件安装等）从 pinned 中取得 URL，最后使用 direct_url.json（PEP 610）
+pub(crate) fn collect(
+    site: &std::path::Path,
+    requested: &[String],
+    reports: &[serde_json::Value],
+    pinned: &[LockedPackage],
+) -> Result<Vec<InstalledDistribution>> {
+    let mut result = vec![];
+    for (dist_info, metadata) in crate::metadata::installed(site)? {
+        let key = normalize_name(&metadata.name);
+        let url = reports
+            .iter()
+            .flat_map(|i| i["install"].as_array().into_iter().flatten())
+            .find(|i| normalize_name(i["metadata"]["name"].as_str().unwrap_or_default()) == key)
+            .and_then(|i| i["download_info"]["url"].as_str())
+            .map(|i| i.to_string())
+            .or_else(|| {
+                pinned
+                    .iter()
+                    .find(|i| i.name == key && i.version == metadata.version)
+                    .and_then(|i| i.url.clone())
+            })
+            .or_else(|| direct_url(&dist_info));
+        let tags = match std::fs::read_to_string(dist_info.join("WHEEL")) {
+            Ok(content) => content
+                .lines()
+                .filter_map(|i| i.strip_prefix("Tag:"))
+                .map(|i| i.trim().to_string())
+                .collect(),
+            Err(_) => vec![],
+        };
+        // 没有 License 字段时从分类中取得，如 License :: OSI Approved :: MIT License
+        let license = metadata.license.clone().or_else(|| {
+            metadata
+                .classifiers
+                .iter()
+                .filter(|i| i.starts_with("License ::"))
+                .filter_map(|i| i.rsplit("::").next())
+                .map(|i| i.trim().to_string())
+                .next()
+        });
+        result.push(InstalledDistribution {
+            requested: requested.contains(&key),
+            name: metadata.name,
+            version: metadata.version,
+            url,
+            tags,
+            license,
+        });
+    }
+    result.sort_by_key(|i| normalize_name(&i.name));
+    Ok(result)
+}
+
+fn direct_url(dist_info: &std::path::Path) -> Option<String> {
+    let data = std::fs::read(dist_info.join("direct_url.json")).ok()?;
+    let value: serde_json::Value = serde_json::from_slice(&data).ok()?;
+    value["url"].as_str().map(|i| i.to_string())
+}
+
+// 按列对齐的安装结果表格，每项一行
+pub fn table(distributions: &[InstalledDistribution]) -> Vec<String> {
+    let rows: Vec<[String; 4]> = distributions
+        .iter()
+        .map(|i| {
+            [
+                i.name.clone(),
+                i.version.clone(),
+                if i.requested {
+                    "requested"
+                } else {
+                    "dependency"
+                }
+                .to_string(),
+                i.license.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    align(["Package", "Version", "Type", "License"], rows)
+}
+
+// 按列对齐，第一行为表头，第二行为分隔线
+pub(crate) fn align<const N: usize>(header: [&str; N], rows: Vec<[String; N]>) -> Vec<String> {
+    let header = header.map(|i| i.to_string());
+    let mut widths = [0usize; N];
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (n, i) in row.iter().enumerate() {
+            widths[n] = widths[n].max(i.chars().count());
+        }
+    }
+    let line = |row: &[String; N]| {
+        let mut s = String::new();
+        for (n, i) in row.iter().enumerate() {
+            s += i;
+            if n + 1 < row.len() {
+                s += &" ".repeat(widths[n] - i.chars().count() + 1);
+            }
+        }
+        s.trim_end().to_string()
+    };
+    let mut result = vec![line(&header)];
+    result.push(line(&widths.map(|i| "-".repeat(i))));
+    result.extend(rows.iter().map(line));
+    result
+}
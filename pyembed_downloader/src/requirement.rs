@@ -0,0 +1,81 @@
+// PEP 508 依赖声明，如 requests[socks]>=2.0; python_version >= "3.8"
+
+use crate::pep440::Specifiers;
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Requirement {
+    // 规范化后的名称
+    pub name: String,
+    pub extras: Vec<String>,
+    pub specifiers: Specifiers,
+    pub url: Option<String>,
+    pub marker: Option<String>,
+}
+
+impl std::str::FromStr for Requirement {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Requirement> {
+        let caps = match crate::utility::regex_find(
+            r"^\s*([A-Za-z0-9](?:[A-Za-z0-9._-]*[A-Za-z0-9])?)\s*(?:\[([^\]]*)\])?\s*(.*)$",
+            s,
+        ) {
+            Some(caps) => caps,
+            None => return Err(format!("无效的依赖声明：{}", s).into()),
+        };
+        let name = normalize_name(caps.get(1).unwrap().as_str());
+        let extras = caps
+            .get(2)
+            .map(|i| {
+                i.as_str()
+                    .split(',')
+                    .map(normalize_name)
+                    .filter(|i| !i.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let rest = caps.get(3).map(|i| i.as_str().trim()).unwrap_or_default();
+
+        let (url, spec, marker) = if let Some(rest) = rest.strip_prefix('@') {
+            // URL 与环境标记之间必须有空白
+            let rest = rest.trim_start();
+            let (url, marker) = match rest.find(char::is_whitespace) {
+                Some(n) => (&rest[..n], rest[n..].trim_start().strip_prefix(';')),
+                None => (rest, None),
+            };
+            (Some(url.to_string()), "", marker)
+        } else {
+            match rest.split_once(';') {
+                Some((spec, marker)) => (None, spec, Some(marker)),
+                None => (None, rest, None),
+            }
+        };
+        let spec = spec.trim();
+        let spec = spec
+            .strip_prefix('(')
+            .and_then(|i| i.strip_suffix(')'))
+            .unwrap_or(spec);
+        let marker = marker
+            .map(|i| i.trim().to_string())
+            .filter(|i| !i.is_empty());
+        Ok(Requirement {
+            name,
+            extras,
+            specifiers: spec.parse()?,
+            url,
+            marker,
+        })
+    }
+}
+
+impl Requirement {
+    // 在目标环境中是否需要这个依赖，extras 为依赖方被请求的 extra
+    pub fn applies(&self, env: &crate::markers::Environment, extras: &[String]) -> Result<bool> {
+        match &self.marker {
+            Some(marker) => env.evaluate(marker, extras),
+            None => Ok(true),
+        }
+    }
+}
@@ -0,0 +1,830 @@
+// 不运行 pip 的依赖解析，从本地目录和 simple API 索引（PEP 691 JSON 或 PEP 503 HTML）中查找 wheel
+// 不做回溯，每个包选择满足当前约束的最高版本，遇到冲突时报错
+
+use crate::lock::LockedPackage;
+use crate::markers::Environment;
+use crate::metadata::Metadata;
+use crate::pep440::{Specifiers, Version};
+use crate::pyproject::normalize_name;
+use crate::requirement::Requirement;
+use crate::wheel::WheelName;
+use crate::Result;
+
+pub(crate) const DEFAULT_INDEX: &str = "https://pypi.org/simple/";
+
+// 一个可供安装的 wheel
+#[derive(Debug, Clone)]
+pub(crate) struct Candidate {
+    // 规范化后的名称
+    pub name: String,
+    pub version: Version,
+    pub filename: String,
+    // 本地文件为 file:// URL
+    pub url: String,
+    pub sha256: Option<String>,
+    // 本地文件的路径
+    path: Option<std::path::PathBuf>,
+    requires_python: Option<String>,
+    yanked: bool,
+    // 索引是否提供了单独的元数据文件（PEP 658），以及它的哈希
+    has_metadata: bool,
+    metadata_sha256: Option<String>,
+    // 与目标平台的匹配程度，见 WheelName::score
+    score: u32,
+}
+
+pub(crate) struct Finder {
+    local: Vec<Candidate>,
+    indexes: Vec<String>,
+    python: String,
+    is32: bool,
+    // 从索引下载的 wheel 保存在此目录
+    download_dir: std::path::PathBuf,
+    client: reqwest::Client,
+    pages: std::collections::HashMap<String, Vec<Candidate>>,
+}
+
+impl Finder {
+    // local 为本地 wheel 目录，indexes 为空时只使用本地目录
+    pub fn new(
+        local: &[std::path::PathBuf],
+        indexes: &[String],
+        python: &str,
+        is32: bool,
+        download_dir: &std::path::Path,
+    ) -> Result<Finder> {
+        let mut candidates = vec![];
+        for dir in local {
+            if !dir.is_dir() {
+                return Err(format!("{} 不是目录", dir.display()).into());
+            }
+            for i in std::fs::read_dir(dir)? {
+                let path = i?.path();
+                let filename = path.file_name().unwrap().to_string_lossy().to_string();
+                if !filename.ends_with(".whl") {
+                    continue;
+                }
+                match local_candidate(&path, python, is32)? {
+                    Some(candidate) => candidates.push(candidate),
+                    None => info!("跳过：{}", filename),
+                }
+            }
+        }
+        Ok(Finder {
+            local: candidates,
+            indexes: indexes
+                .iter()
+                .map(|i| format!("{}/", i.trim_end_matches('/')))
+                .collect(),
+            python: python.to_string(),
+            is32,
+            download_dir: download_dir.to_path_buf(),
+            client: reqwest::Client::new(),
+            pages: Default::default(),
+        })
+    }
+
+    // name @ file:///... 形式直接指定的本地 wheel
+    pub fn direct(&self, url: &str) -> Result<Candidate> {
+        let path = match reqwest::Url::parse(url)
+            .ok()
+            .and_then(|i| i.to_file_path().ok())
+        {
+            Some(path) if url.starts_with("file:") => path,
+            _ => return Err(format!("不支持 URL 依赖：{}", url).into()),
+        };
+        match local_candidate(&path, &self.python, self.is32)? {
+            Some(candidate) => Ok(candidate),
+            None => Err(format!("{} 与目标平台不兼容", path.display()).into()),
+        }
+    }
+
+    // 所有与目标平台兼容的候选，本地的排在前面
+    pub async fn candidates(&mut self, name: &str) -> Result<Vec<Candidate>> {
+        let name = normalize_name(name);
+        let mut result: Vec<Candidate> = self
+            .local
+            .iter()
+            .filter(|i| i.name == name)
+            .cloned()
+            .collect();
+        if !self.indexes.is_empty() {
+            if !self.pages.contains_key(&name) {
+                // 与 pip 相同，所有索引中的文件合并在一起比较
+                let mut page = vec![];
+                for index in self.indexes.iter() {
+                    page.extend(self.fetch_page(index, &name).await?);
+                }
+                self.pages.insert(name.clone(), page);
+            }
+            result.extend(self.pages[&name].iter().cloned());
+        }
+        Ok(result)
+    }
+
+    async fn fetch_page(&self, index: &str, name: &str) -> Result<Vec<Candidate>> {
+        let url = reqwest::Url::parse(index)?.join(&format!("{}/", name))?;
+        info!("查询：{}", url);
+        let res = self
+            .client
+            .get(url)
+            .header(
+                reqwest::header::ACCEPT,
+                "application/vnd.pypi.simple.v1+json, text/html;q=0.1",
+            )
+            .send()
+            .await?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(vec![]);
+        }
+        if !res.status().is_success() {
+            let code: u16 = res.status().into();
+            return Err(format!("http request failed with status code {}", code).into());
+        }
+        let base = res.url().clone();
+        let content_type = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|i| i.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = res.text().await?;
+        let files = if content_type.contains("json") {
+            parse_json_page(&body)?
+        } else {
+            parse_html_page(&body)
+        };
+
+        let mut result = vec![];
+        for mut file in files {
+            let wheel: WheelName = match file.filename.parse() {
+                Ok(wheel) => wheel,
+                // 源码包等
+                Err(_) => continue,
+            };
+            if wheel.name != name {
+                continue;
+            }
+            let score = match wheel.score(&self.python, self.is32) {
+                Some(score) => score,
+                None => continue,
+            };
+            file.url = base.join(&file.url)?.to_string();
+            // URL 中的 #sha256=... 也是哈希
+            if let Some((url, fragment)) = file.url.split_once('#') {
+                if file.sha256.is_none() {
+                    file.sha256 = fragment.strip_prefix("sha256=").map(|i| i.to_string());
+                }
+                file.url = url.to_string();
+            }
+            result.push(Candidate {
+                name: wheel.name,
+                version: wheel.version,
+                filename: file.filename,
+                url: file.url,
+                sha256: file.sha256,
+                path: None,
+                requires_python: file.requires_python,
+                yanked: file.yanked,
+                has_metadata: file.has_metadata,
+                metadata_sha256: file.metadata_sha256,
+                score,
+            });
+        }
+        Ok(result)
+    }
+
+    // 优先使用本地文件或索引提供的元数据文件，否则需要下载整个 wheel
+    pub async fn metadata(
+        &self,
+        candidate: &Candidate,
+        progress_callback: &dyn Fn(i64, i64),
+    ) -> Result<Metadata> {
+        if candidate.path.is_none() && candidate.has_metadata {
+            let url = format!("{}.metadata", candidate.url);
+            let data = self
+                .client
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+            if let Some(hash) = &candidate.metadata_sha256 {
+                if !crate::utility::sha256_hex(&data).eq_ignore_ascii_case(hash) {
+                    return Err(format!("{} 的哈希不匹配", url).into());
+                }
+            }
+            return Ok(crate::metadata::parse(&String::from_utf8_lossy(&data)));
+        }
+        let path = self.fetch(candidate, progress_callback).await?;
+        crate::wheel::read_metadata(&path)
+    }
+
+    // 取得 wheel 文件，从索引下载的文件会校验哈希并保存到下载目录
+    pub async fn fetch(
+        &self,
+        candidate: &Candidate,
+        progress_callback: &dyn Fn(i64, i64),
+    ) -> Result<std::path::PathBuf> {
+        if let Some(path) = &candidate.path {
+            return Ok(path.clone());
+        }
+        let path = self.download_dir.join(&candidate.filename);
+        let matches = |data: &[u8]| match &candidate.sha256 {
+            Some(hash) => crate::utility::sha256_hex(data).eq_ignore_ascii_case(hash),
+            None => true,
+        };
+        if path.exists() && candidate.sha256.is_some() && matches(&std::fs::read(&path)?) {
+            return Ok(path);
+        }
+        info!("下载：{}", candidate.filename);
+        let data = crate::download_progress(candidate.url.as_str(), progress_callback).await?;
+        progress_callback(-1, -1);
+        if !matches(&data) {
+            return Err(format!("{} 的哈希不匹配", candidate.filename).into());
+        }
+        std::fs::create_dir_all(&self.download_dir)?;
+        std::fs::write(&path, &data)?;
+        Ok(path)
+    }
+
+    // 查找与锁文件中版本和哈希一致的 wheel
+    pub async fn pinned(&mut self, package: &LockedPackage) -> Result<Candidate> {
+        let version: Version = package.version.parse()?;
+        let hashes: Vec<&str> = package
+            .hashes
+            .iter()
+            .filter_map(|i| i.strip_prefix("sha256:"))
+            .collect();
+        if hashes.is_empty() {
+            return Err(format!(
+                "锁文件中 {} 没有记录 sha256 哈希，无法按锁文件安装",
+                package.name
+            )
+            .into());
+        }
+        let mut candidates: Vec<Candidate> = self
+            .candidates(&package.name)
+            .await?
+            .into_iter()
+            // 按 PEP 440 比较，锁文件中的 1.0 与索引中的 1.0.0 是同一版本
+            .filter(|i| i.version.cmp(&version) == std::cmp::Ordering::Equal)
+            .collect();
+        candidates.sort_by_key(|i| std::cmp::Reverse(i.score));
+        for mut i in candidates {
+            if let (Some(path), None) = (&i.path, &i.sha256) {
+                i.sha256 = Some(crate::utility::sha256_hex(&std::fs::read(path)?));
+            }
+            if let Some(hash) = &i.sha256 {
+                if hashes.iter().any(|h| h.eq_ignore_ascii_case(hash)) {
+                    return Ok(i);
+                }
+            }
+        }
+        Err(format!(
+            "找不到与锁文件哈希一致的 {}=={}",
+            package.name, package.version
+        )
+        .into())
+    }
+}
+
+// 与目标平台不兼容或不是 wheel 时返回 None
+fn local_candidate(path: &std::path::Path, python: &str, is32: bool) -> Result<Option<Candidate>> {
+    let filename = path.file_name().unwrap().to_string_lossy().to_string();
+    let wheel: WheelName = match filename.parse() {
+        Ok(wheel) => wheel,
+        Err(_) => return Ok(None),
+    };
+    let score = match wheel.score(python, is32) {
+        Some(score) => score,
+        None => return Ok(None),
+    };
+    let path = std::fs::canonicalize(path)?;
+    Ok(Some(Candidate {
+        name: wheel.name,
+        version: wheel.version,
+        url: reqwest::Url::from_file_path(&path)
+            .map(|i| i.to_string())
+            .unwrap_or_default(),
+        filename,
+        sha256: None,
+        path: Some(path),
+        requires_python: None,
+        yanked: false,
+        has_metadata: false,
+        metadata_sha256: None,
+        score,
+    }))
+}
+
+// 索引页面中的一个文件
+struct IndexFile {
+    filename: String,
+    url: String,
+    sha256: Option<String>,
+    requires_python: Option<String>,
+    yanked: bool,
+    has_metadata: bool,
+    metadata_sha256: Option<String>,
+}
+
+fn parse_json_page(body: &str) -> Result<Vec<IndexFile>> {
+    let page: serde_json::Value = serde_json::from_str(body)?;
+    let mut result = vec![];
+    for file in page["files"].as_array().into_iter().flatten() {
+        // PEP 714 改名为 core-metadata，旧的索引仍使用 dist-info-metadata
+        let metadata = if file.get("core-metadata").is_some() {
+            &file["core-metadata"]
+        } else {
+            &file["dist-info-metadata"]
+        };
+        result.push(IndexFile {
+            filename: file["filename"].as_str().unwrap_or_default().to_string(),
+            url: file["url"].as_str().unwrap_or_default().to_string(),
+            sha256: file["hashes"]["sha256"].as_str().map(|i| i.to_string()),
+            requires_python: file["requires-python"]
+                .as_str()
+                .filter(|i| !i.trim().is_empty())
+                .map(|i| i.to_string()),
+            yanked: match &file["yanked"] {
+                serde_json::Value::Bool(b) => *b,
+                serde_json::Value::String(_) => true,
+                _ => false,
+            },
+            has_metadata: match metadata {
+                serde_json::Value::Bool(b) => *b,
+                serde_json::Value::Object(_) => true,
+                _ => false,
+            },
+            metadata_sha256: metadata["sha256"].as_str().map(|i| i.to_string()),
+        });
+    }
+    Ok(result)
+}
+
+fn parse_html_page(body: &str) -> Vec<IndexFile> {
+    let anchor = regex::RegexBuilder::new(r"<a\s([^>]*)>([^<]*)</a>")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    let attribute = regex::Regex::new(r#"([\w-]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'))?"#).unwrap();
+    let mut result = vec![];
+    for caps in anchor.captures_iter(body) {
+        let mut attrs = std::collections::HashMap::new();
+        for a in attribute.captures_iter(&caps[1]) {
+            let value = a
+                .get(2)
+                .or_else(|| a.get(3))
+                .map(|i| i.as_str())
+                .unwrap_or_default();
+            attrs.insert(a[1].to_ascii_lowercase(), unescape_html(value));
+        }
+        let url = match attrs.get("href") {
+            Some(url) => url.clone(),
+            None => continue,
+        };
+        let metadata = attrs
+            .get("data-core-metadata")
+            .or_else(|| attrs.get("data-dist-info-metadata"));
+        result.push(IndexFile {
+            filename: unescape_html(caps[2].trim()),
+            url,
+            sha256: None,
+            requires_python: attrs
+                .get("data-requires-python")
+                .filter(|i| !i.trim().is_empty())
+                .cloned(),
+            yanked: attrs.contains_key("data-yanked"),
+            has_metadata: metadata.is_some_and(|i| i != "false"),
+            metadata_sha256: metadata
+                .and_then(|i| i.strip_prefix("sha256="))
+                .map(|i| i.to_string()),
+        });
+    }
+    result
+}
+
+fn unescape_html(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+// 解析依赖，得到要安装的 wheel 列表
+// skip 中的包已经按锁文件安装，不再处理；返回值中的 bool 表示是否为用户直接请求的包
+// 按广度优先的顺序处理，每个包在第一次遇到时就选定版本，之后不再更改（不回溯）
+// 后面的依赖对已选定的包提出更严格的约束时直接报错，需要用户在请求中明确指定该包的版本
+pub(crate) async fn resolve(
+    finder: &mut Finder,
+    requirements: &[String],
+    env: &Environment,
+    skip: &[String],
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<Vec<(Candidate, bool)>> {
+    struct Selected {
+        candidate: Candidate,
+        metadata: Metadata,
+        extras: Vec<String>,
+        requested: bool,
+    }
+    let python: Version = env.python.parse()?;
+    let mut selected: Vec<Selected> = vec![];
+    let mut queue: std::collections::VecDeque<(Requirement, bool)> = Default::default();
+    for i in requirements {
+        let req: Requirement = i.parse()?;
+        // 用户请求中的环境标记同样需要判断，如 pywin32; sys_platform == "win32"
+        if !req.applies(env, &[])? {
+            info!("环境标记不满足，跳过：{}", i);
+            continue;
+        }
+        queue.push_back((req, true));
+    }
+
+    while let Some((req, requested)) = queue.pop_front() {
+        if skip.contains(&req.name) {
+            continue;
+        }
+        let new_extras: Vec<String>;
+        if let Some(s) = selected.iter_mut().find(|i| i.candidate.name == req.name) {
+            if !req.specifiers.contains(&s.candidate.version) {
+                // 不回溯，见函数说明
+                return Err(format!(
+                    "依赖冲突：已选择 {}=={}，但还要求 {}{}，请在依赖列表中明确指定 {} 的版本",
+                    req.name, s.candidate.version, req.name, req.specifiers, req.name
+                )
+                .into());
+            }
+            s.requested |= requested;
+            new_extras = req
+                .extras
+                .iter()
+                .filter(|i| !s.extras.contains(i))
+                .cloned()
+                .collect();
+            if new_extras.is_empty() {
+                continue;
+            }
+            s.extras.extend(new_extras.iter().cloned());
+        } else {
+            let (candidate, metadata) = match &req.url {
+                Some(url) => {
+                    let candidate = finder.direct(url)?;
+                    let metadata = finder.metadata(&candidate, progress_callback).await?;
+                    (candidate, metadata)
+                }
+                None => select(finder, &req, &python, progress_callback).await?,
+            };
+            info!("{}=={}", candidate.name, candidate.version);
+            // 空字符串表示不带 extra 的基础依赖
+            new_extras = std::iter::once(String::new())
+                .chain(req.extras.iter().cloned())
+                .collect();
+            selected.push(Selected {
+                candidate,
+                metadata,
+                extras: req.extras.clone(),
+                requested,
+            });
+        }
+
+        let s = selected
+            .iter()
+            .find(|i| i.candidate.name == req.name)
+            .unwrap();
+        for extra in new_extras {
+            let extras: Vec<String> = if extra.is_empty() {
+                vec![]
+            } else {
+                vec![extra]
+            };
+            for dep in s.metadata.requires_dist.iter() {
+                let dep: Requirement = dep.parse()?;
+                // extra 引入的依赖中跳过基础依赖，它们已经处理过了
+                if dep.applies(env, &extras)? && (extras.is_empty() || !dep.applies(env, &[])?) {
+                    queue.push_back((dep, false));
+                }
+            }
+        }
+    }
+    Ok(selected
+        .into_iter()
+        .map(|i| (i.candidate, i.requested))
+        .collect())
+}
+
+// 选择满足约束的最高版本，同一版本中优先使用与平台匹配程度最高的 wheel
+async fn select(
+    finder: &mut Finder,
+    req: &Requirement,
+    python: &Version,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<(Candidate, Metadata)> {
+    let python_matches = |spec: &Option<String>| -> Result<bool> {
+        match spec {
+            Some(spec) => Ok(spec.parse::<Specifiers>()?.contains(python)),
+            None => Ok(true),
+        }
+    };
+    let exact = req.specifiers.is_exact();
+    let mut candidates = vec![];
+    for i in finder.candidates(&req.name).await? {
+        // 被撤回的版本只有在精确指定时才能使用（PEP 592）
+        if (i.yanked && !exact)
+            || !req.specifiers.contains(&i.version)
+            || !python_matches(&i.requires_python)?
+        {
+            continue;
+        }
+        candidates.push(i);
+    }
+    // 没有指定预发布版本时优先使用正式版本
+    if !req.specifiers.allows_prerelease() && candidates.iter().any(|i| !i.version.is_prerelease())
+    {
+        candidates.retain(|i| !i.version.is_prerelease());
+    }
+    candidates.sort_by(|a, b| (&b.version, b.score).cmp(&(&a.version, a.score)));
+    for i in candidates {
+        let metadata = finder.metadata(&i, progress_callback).await?;
+        if !python_matches(&metadata.requires_python)? {
+            info!(
+                "{} 要求 Python {}，跳过",
+                i.filename,
+                metadata.requires_python.unwrap()
+            );
+            continue;
+        }
+        return Ok((i, metadata));
+    }
+    Err(format!("找不到满足 {}{} 的 wheel", req.name, req.specifiers).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试中没有网络请求，future 第一次 poll 就会完成，不需要 tokio
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        fn noop(_: *const ()) {}
+        fn clone(p: *const ()) -> std::task::RawWaker {
+            std::task::RawWaker::new(p, &VTABLE)
+        }
+        static VTABLE: std::task::RawWakerVTable =
+            std::task::RawWakerVTable::new(clone, noop, noop, noop);
+        let waker = unsafe {
+            std::task::Waker::from_raw(std::task::RawWaker::new(std::ptr::null(), &VTABLE))
+        };
+        let mut cx = std::task::Context::from_waker(&waker);
+        let mut f = Box::pin(f);
+        loop {
+            if let std::task::Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+                return v;
+            }
+        }
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pyembed-resolver-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // 只含 METADATA 的 wheel，解析依赖时只需要读取它
+    fn write_wheel(dir: &std::path::Path, filename: &str, metadata: &str) -> std::path::PathBuf {
+        use std::io::Write;
+        let wheel: WheelName = filename.parse().unwrap();
+        let path = dir.join(filename);
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        zip.start_file(
+            format!("{}-{}.dist-info/METADATA", wheel.name, wheel.version),
+            zip::write::FileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(metadata.as_bytes()).unwrap();
+        zip.finish().unwrap();
+        path
+    }
+
+    fn resolve_names(finder: &mut Finder, requirements: &[&str]) -> Result<Vec<String>> {
+        let requirements: Vec<String> = requirements.iter().map(|i| i.to_string()).collect();
+        let env = Environment::new("3.11.7", false);
+        let result = block_on(resolve(finder, &requirements, &env, &[], &|_, _| {}))?;
+        Ok(result
+            .into_iter()
+            .map(|(i, requested)| {
+                format!(
+                    "{}=={}{}",
+                    i.name,
+                    i.version,
+                    if requested { "" } else { " (dep)" }
+                )
+            })
+            .collect())
+    }
+
+    #[test]
+    fn json_page() {
+        let files = parse_json_page(
+            r#"{"meta": {"api-version": "1.1"}, "name": "demo", "files": [
+                {"filename": "demo-1.0-py3-none-any.whl", "url": "../../files/demo-1.0-py3-none-any.whl",
+                 "hashes": {"sha256": "aa"}, "requires-python": ">=3.8", "yanked": false,
+                 "core-metadata": {"sha256": "bb"}},
+                {"filename": "demo-0.9-py3-none-any.whl", "url": "demo-0.9-py3-none-any.whl",
+                 "hashes": {}, "requires-python": " ", "yanked": "broken",
+                 "dist-info-metadata": true},
+                {"filename": "demo-0.8.tar.gz", "url": "demo-0.8.tar.gz", "hashes": {}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].url, "../../files/demo-1.0-py3-none-any.whl");
+        assert_eq!(files[0].sha256.as_deref(), Some("aa"));
+        assert_eq!(files[0].requires_python.as_deref(), Some(">=3.8"));
+        assert!(!files[0].yanked);
+        assert!(files[0].has_metadata);
+        assert_eq!(files[0].metadata_sha256.as_deref(), Some("bb"));
+        assert_eq!(files[1].sha256, None);
+        assert_eq!(files[1].requires_python, None);
+        assert!(files[1].yanked);
+        assert!(files[1].has_metadata);
+        assert_eq!(files[1].metadata_sha256, None);
+        assert!(!files[2].has_metadata);
+    }
+
+    #[test]
+    fn html_page() {
+        let files = parse_html_page(
+            r#"<!DOCTYPE html><html><body>
+            <a href="/files/demo-1.0-py3-none-any.whl#sha256=aa" data-requires-python="&gt;=3.8"
+               data-core-metadata="sha256=bb">demo-1.0-py3-none-any.whl</a><br/>
+            <A HREF='demo-0.9-py3-none-any.whl' data-yanked="">demo-0.9-py3-none-any.whl</A>
+            <a href="demo-0.8-py3-none-any.whl" data-dist-info-metadata="false">demo-0.8-py3-none-any.whl</a>
+            <a name="nohref">x</a>
+            </body></html>"#,
+        );
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].filename, "demo-1.0-py3-none-any.whl");
+        assert_eq!(files[0].url, "/files/demo-1.0-py3-none-any.whl#sha256=aa");
+        assert_eq!(files[0].requires_python.as_deref(), Some(">=3.8"));
+        assert!(files[0].has_metadata);
+        assert_eq!(files[0].metadata_sha256.as_deref(), Some("bb"));
+        assert!(!files[0].yanked);
+        assert!(files[1].yanked);
+        assert!(!files[1].has_metadata);
+        assert!(!files[2].has_metadata);
+    }
+
+    #[test]
+    fn wheel_selection() {
+        let dir = temp_dir("select");
+        let metadata = "Metadata-Version: 2.1\nName: demo\nVersion: 1.0\n";
+        write_wheel(&dir, "demo-1.0-py3-none-any.whl", metadata);
+        write_wheel(&dir, "demo-1.0-cp311-cp311-win_amd64.whl", metadata);
+        write_wheel(&dir, "demo-1.0-cp311-cp311-win32.whl", metadata);
+        // 与目标平台不兼容的更高版本不会被选中
+        write_wheel(
+            &dir,
+            "demo-2.0-cp310-cp310-win_amd64.whl",
+            "Metadata-Version: 2.1\nName: demo\nVersion: 2.0\n",
+        );
+        let mut finder =
+            Finder::new(std::slice::from_ref(&dir), &[], "3.11.7", false, &dir).unwrap();
+        let env = Environment::new("3.11.7", false);
+        let result = block_on(resolve(
+            &mut finder,
+            &["demo".to_string()],
+            &env,
+            &[],
+            &|_, _| {},
+        ))
+        .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0.filename, "demo-1.0-cp311-cp311-win_amd64.whl");
+        assert!(result[0].1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn yanked_and_requires_python() {
+        let dir = temp_dir("filter");
+        let mut page = vec![];
+        for (version, requires_python, yanked, metadata_python) in [
+            ("4.0", None, false, Some(">=3.12")),
+            ("3.0", None, true, None),
+            ("2.0", Some(">=3.12"), false, None),
+            ("1.0", Some(">=3.8"), false, None),
+        ] {
+            let mut metadata = format!("Metadata-Version: 2.1\nName: demo\nVersion: {}\n", version);
+            if let Some(python) = metadata_python {
+                metadata += &format!("Requires-Python: {}\n", python);
+            }
+            let path = write_wheel(
+                &dir,
+                &format!("demo-{}-py3-none-any.whl", version),
+                &metadata,
+            );
+            let mut candidate = local_candidate(&path, "3.11.7", false).unwrap().unwrap();
+            candidate.requires_python = requires_python.map(|i| i.to_string());
+            candidate.yanked = yanked;
+            page.push(candidate);
+        }
+        // 索引页面已经缓存，不会发出请求
+        let mut finder = Finder::new(
+            &[],
+            &["http://localhost/simple".to_string()],
+            "3.11.7",
+            false,
+            &dir,
+        )
+        .unwrap();
+        finder.pages.insert("demo".to_string(), page);
+
+        // 4.0 的 METADATA 要求 3.12，3.0 被撤回，2.0 的索引信息要求 3.12
+        assert_eq!(
+            resolve_names(&mut finder, &["demo"]).unwrap(),
+            ["demo==1.0"]
+        );
+        // 精确指定时可以使用被撤回的版本
+        assert_eq!(
+            resolve_names(&mut finder, &["demo==3.0"]).unwrap(),
+            ["demo==3.0"]
+        );
+        assert!(resolve_names(&mut finder, &["demo==2.0"]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pinned_zero_padded() {
+        let dir = temp_dir("pinned");
+        let path = write_wheel(
+            &dir,
+            "demo-1.0.0-py3-none-any.whl",
+            "Metadata-Version: 2.1\nName: demo\nVersion: 1.0.0\n",
+        );
+        let hash = crate::utility::sha256_hex(&std::fs::read(&path).unwrap());
+        let mut finder =
+            Finder::new(std::slice::from_ref(&dir), &[], "3.11.7", false, &dir).unwrap();
+        let mut package = LockedPackage {
+            name: "demo".to_string(),
+            version: "1.0".to_string(),
+            url: None,
+            hashes: vec![format!("sha256:{}", hash)],
+        };
+        let candidate = block_on(finder.pinned(&package)).unwrap();
+        assert_eq!(candidate.filename, "demo-1.0.0-py3-none-any.whl");
+        package.hashes = vec![format!("sha256:{}", "0".repeat(64))];
+        assert!(block_on(finder.pinned(&package)).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dependencies_and_conflict() {
+        let dir = temp_dir("conflict");
+        write_wheel(
+            &dir,
+            "app-1.0-py3-none-any.whl",
+            "Metadata-Version: 2.1\nName: app\nVersion: 1.0\n\
+             Requires-Dist: lib<2\n\
+             Requires-Dist: extra-only; extra == \"cli\"\n\
+             Requires-Dist: posix-only; sys_platform == \"linux\"\n",
+        );
+        for version in ["1.0", "2.0"] {
+            write_wheel(
+                &dir,
+                &format!("lib-{}-py3-none-any.whl", version),
+                &format!("Metadata-Version: 2.1\nName: lib\nVersion: {}\n", version),
+            );
+        }
+        let mut finder =
+            Finder::new(std::slice::from_ref(&dir), &[], "3.11.7", false, &dir).unwrap();
+
+        assert_eq!(
+            resolve_names(&mut finder, &["app"]).unwrap(),
+            ["app==1.0", "lib==1.0 (dep)"]
+        );
+        // 用户请求中不满足环境标记的依赖被跳过
+        assert_eq!(
+            resolve_names(&mut finder, &["app", "missing; sys_platform == 'linux'"]).unwrap(),
+            ["app==1.0", "lib==1.0 (dep)"]
+        );
+        // 先选定了 lib 2.0，不回溯，报告冲突
+        let err = resolve_names(&mut finder, &["lib", "app"])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("依赖冲突"), "{}", err);
+        // 明确指定版本后可以解析
+        assert_eq!(
+            resolve_names(&mut finder, &["lib<2", "app"]).unwrap(),
+            ["lib==1.0", "app==1.0"]
+        );
+        // extra 引入的依赖找不到时报错，说明 extra 被处理了
+        assert!(resolve_names(&mut finder, &["app[cli]"]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,432 @@
+// 从应用的入口脚本分析导入的模块，删除 site-packages 和标准库 pythonXY.zip 中没有用到的模块
+// 静态分析使用本机 Python 的 modulefinder，本机 Python 与目标的次版本必须相同，标准库取自本机 Python
+// 记录运行时在目标 python.exe 中运行入口脚本，退出时记录 sys.modules，能得到动态导入的模块
+// 两种方式都可能遗漏动态导入的模块，需要用 keep_modules 保留
+// 分析结果和删除的文件保存为 JSON 报告
+
+use crate::config::{Backend, Config};
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+const STATIC_SCRIPT: &str = r#"
+import os
+import sys
+import json
+import modulefinder
+
+OUTPUT, SITEDIR, SCRIPT = sys.argv[1:4]
+
+# 只使用本机 Python 的标准库，不使用本机的 site-packages
+path = [os.path.dirname(os.path.abspath(SCRIPT)), SITEDIR]
+path += [i for i in sys.path[1:] if "site-packages" not in i and "dist-packages" not in i]
+finder = modulefinder.ModuleFinder(path)
+# 启动时导入的模块
+for name in ["site", "encodings"]:
+    try:
+        finder.import_hook(name)
+    except ImportError:
+        pass
+finder.run_script(SCRIPT)
+with open(OUTPUT, "w", encoding="utf-8") as f:
+    json.dump({"modules": sorted(finder.modules), "missing": sorted(finder.badmodules)}, f)
+"#;
+
+const RUN_SCRIPT: &str = r#"
+import sys
+import json
+import runpy
+import atexit
+
+OUTPUT, SCRIPT = sys.argv[1:3]
+
+def dump():
+    with open(OUTPUT, "w", encoding="utf-8") as f:
+        json.dump({"modules": sorted(sys.modules), "missing": []}, f)
+
+atexit.register(dump)
+sys.argv = [SCRIPT]
+runpy.run_path(SCRIPT, run_name="__main__")
+"#;
+
+// 始终保留的模块，编码按名称动态导入
+const ALWAYS_KEEP: [&str; 1] = ["encodings"];
+
+// 清理时会按 RECORD 卸载，不在这里删除
+const EXCLUDED: [&str; 3] = ["pip", "setuptools", "wheel"];
+
+#[derive(serde::Serialize)]
+pub(crate) struct Shaker {
+    entry_script: std::path::PathBuf,
+    mode: &'static str,
+    // 用到的模块
+    modules: std::collections::BTreeSet<String>,
+    // 静态分析时找不到的模块，可能是平台相关或动态导入的模块
+    missing: Vec<String>,
+    keep: Vec<String>,
+    // 删除的文件，site-packages 中为相对路径，标准库为 pythonXY.zip 中的路径
+    removed_site_packages: Vec<String>,
+    removed_stdlib: Vec<String>,
+    removed_bytes: u64,
+}
+
+// 要在编译之前调用，静态分析需要 site-packages 中的源文件
+pub(crate) fn trace(config: &Config, dir: &std::path::Path, pyver: &str) -> Result<Shaker> {
+    let script = match &config.entry_script {
+        Some(script) => std::env::current_dir()?.join(script),
+        None => return Err("没有指定入口脚本".into()),
+    };
+    if !script.is_file() {
+        return Err(format!("找不到入口脚本：{}", script.display()).into());
+    }
+    let mut cachedir = config.cache_dir.clone();
+    if cachedir.is_relative() {
+        cachedir = std::env::current_dir()?.join(cachedir);
+    }
+    let output = cachedir.join("pyembed-imports.json");
+    let _ = std::fs::remove_file(&output);
+
+    let mut cmd = if config.trace_run {
+        if config.installer == Backend::Native || !cfg!(windows) {
+            return Err(
+                "记录运行需要运行目标 python.exe，只能在 Windows 上使用 pip 或 uv 安装器".into(),
+            );
+        }
+        let mut cmd = crate::new_python_command(dir);
+        cmd.args(["-c", RUN_SCRIPT]);
+        cmd.arg(&output);
+        cmd
+    } else {
+        if !crate::host_python_matches(config, pyver) {
+            return Err("静态分析导入需要与目标次版本相同的本机 Python".into());
+        }
+        let mut cmd = crate::new_host_python_command(config);
+        cmd.args(["-c", STATIC_SCRIPT]);
+        cmd.arg(&output);
+        cmd.arg(dir.join("Lib").join("site-packages"));
+        cmd
+    };
+    cmd.arg(&script);
+    let mut process = cmd.spawn()?;
+    let (t1, t2) = crate::process_output_to_log(&mut process);
+    let status = process.wait()?;
+    t1.join().unwrap();
+    t2.join().unwrap();
+    let data = match std::fs::read(&output) {
+        Ok(data) => data,
+        Err(_) => return Err(format!("分析导入失败 [{}]", status).into()),
+    };
+    std::fs::remove_file(&output)?;
+    if !status.success() {
+        warn!("入口脚本运行失败 [{}]，只使用失败前导入的模块", status);
+    }
+
+    let value: serde_json::Value = serde_json::from_slice(&data)?;
+    let list = |key: &str| -> Vec<String> {
+        value[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|i| i.as_str())
+            .map(|i| i.to_string())
+            .collect()
+    };
+    let mut modules: std::collections::BTreeSet<String> = list("modules").into_iter().collect();
+    // site-packages 中的 .pth 在启动时导入的模块，如 pywin32_bootstrap
+    modules.extend(pth_imports(&dir.join("Lib").join("site-packages"))?);
+    let missing = list("missing");
+    info!("用到 {} 个模块", modules.len());
+    if !missing.is_empty() {
+        info!(
+            "找不到 {} 个模块，可能是平台相关或动态导入的模块",
+            missing.len()
+        );
+    }
+    let mut keep: Vec<String> = ALWAYS_KEEP.iter().map(|i| i.to_string()).collect();
+    keep.extend(config.keep_modules.iter().map(|i| i.trim().to_string()));
+    Ok(Shaker {
+        entry_script: script,
+        mode: if config.trace_run { "run" } else { "static" },
+        modules,
+        missing,
+        keep,
+        removed_site_packages: vec![],
+        removed_stdlib: vec![],
+        removed_bytes: 0,
+    })
+}
+
+impl Shaker {
+    // 模块本身或子模块被用到，或者模块本身、子模块、父包在保留列表中
+    fn is_used(&self, module: &str) -> bool {
+        let is_child =
+            |i: &str, parent: &str| i.strip_prefix(parent).is_some_and(|i| i.starts_with('.'));
+        self.modules
+            .iter()
+            .any(|i| i == module || is_child(i, module))
+            || self
+                .keep
+                .iter()
+                .any(|i| i == module || is_child(i, module) || is_child(module, i))
+    }
+
+    pub(crate) fn shake_site_packages(&mut self, dir: &std::path::Path) -> Result<()> {
+        let site = dir.join("Lib").join("site-packages");
+        let mut excluded = vec![];
+        for (dist_info, metadata) in crate::metadata::installed(&site)? {
+            if EXCLUDED.contains(&normalize_name(&metadata.name).as_str()) {
+                excluded.extend(crate::modify::top_level(&dist_info)?);
+            }
+        }
+        self.walk(&site, "", &excluded)?;
+        Ok(())
+    }
+
+    fn walk(&mut self, site: &std::path::Path, dir: &str, excluded: &[String]) -> Result<()> {
+        let mut entries: Vec<std::fs::DirEntry> =
+            std::fs::read_dir(site.join(dir))?.collect::<std::io::Result<_>>()?;
+        entries.sort_by_key(|i| i.file_name());
+        let package = dir.replace('/', ".");
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if dir.is_empty() && excluded.contains(&name) {
+                continue;
+            }
+            let path = if dir.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", dir, name)
+            };
+            let is_dir = entry.file_type()?.is_dir();
+            // 模块名，不是模块的文件和目录（数据文件、dist-info、*.libs 等）保留
+            let stem = if is_dir {
+                if !has_modules(&entry.path())? {
+                    continue;
+                }
+                name.as_str()
+            } else {
+                if !is_module_file(&name) {
+                    continue;
+                }
+                name.split('.').next().unwrap_or_default()
+            };
+            if stem == "__pycache__" || !is_identifier(stem) {
+                continue;
+            }
+            let module = match (package.is_empty(), stem) {
+                (_, "__init__") | (_, "__main__") => continue,
+                (true, _) => stem.to_string(),
+                (false, _) => format!("{}.{}", package, stem),
+            };
+            if self.is_used(&module) {
+                // 扩展模块中导入的模块无法分析，含有扩展模块的包整个保留
+                if is_dir && !has_extensions(&entry.path())? {
+                    self.walk(site, &path, excluded)?;
+                }
+                continue;
+            }
+            let fullpath = entry.path();
+            let (_, bytes) = crate::utility::disk_usage(&fullpath)?;
+            if is_dir {
+                std::fs::remove_dir_all(&fullpath)?;
+            } else {
+                std::fs::remove_file(&fullpath)?;
+            }
+            info!("删除没有用到的模块：{}", path);
+            self.removed_site_packages.push(path);
+            self.removed_bytes += bytes;
+        }
+        Ok(())
+    }
+
+    // 要在重新编译标准库之后调用
+    pub(crate) fn shake_stdlib(&mut self, dir: &std::path::Path, pyver: &str) -> Result<()> {
+        let zippath = dir.join(format!("python{}.zip", crate::utility::python_xy(pyver)));
+        if !zippath.exists() {
+            return Ok(());
+        }
+        let output = dir.join(format!(
+            "python{}.zip.tmp",
+            crate::utility::python_xy(pyver)
+        ));
+        {
+            let mut source = zip::ZipArchive::new(std::fs::File::open(&zippath)?)?;
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&output)?);
+            for i in 0..source.len() {
+                let file = source.by_index_raw(i)?;
+                let name = file.name().to_string();
+                let module = name
+                    .strip_suffix(".pyc")
+                    .map(|i| i.trim_end_matches("/__init__").replace('/', "."));
+                match module {
+                    Some(module) if !self.is_used(&module) => {
+                        self.removed_bytes += file.compressed_size();
+                        self.removed_stdlib.push(name);
+                    }
+                    _ => zip.raw_copy_file(file)?,
+                }
+            }
+            zip.finish()?;
+        }
+        std::fs::rename(&output, &zippath)?;
+        info!(
+            "从 {} 中删除 {} 个没有用到的模块",
+            zippath.display(),
+            self.removed_stdlib.len()
+        );
+        Ok(())
+    }
+
+    pub(crate) fn save(&self, config: &Config) -> Result<()> {
+        let path = match &config.shake_report {
+            Some(path) => std::env::current_dir()?.join(path),
+            None => std::env::current_dir()?
+                .join(&config.cache_dir)
+                .join("pyembed-shake-report.json"),
+        };
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        info!(
+            "共删除 {} 个模块，{}",
+            self.removed_site_packages.len() + self.removed_stdlib.len(),
+            crate::utility::format_size(self.removed_bytes)
+        );
+        info!("导入分析报告已保存到：{}", path.display());
+        Ok(())
+    }
+}
+
+fn is_module_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".py") || name.ends_with(".pyc") || name.ends_with(".pyd")
+}
+
+// 目录中是否有模块文件，没有时是数据目录
+fn has_modules(dir: &std::path::Path) -> Result<bool> {
+    for i in std::fs::read_dir(dir)? {
+        let i = i?;
+        if i.file_type()?.is_dir() {
+            if has_modules(&i.path())? {
+                return Ok(true);
+            }
+        } else if is_module_file(&i.file_name().to_string_lossy()) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// .pth 中以 import 开头的行会在启动时执行，取出其中导入的模块
+// 如 import sys, types, os;has_mfs = sys.version_info > (3, 5);...
+fn pth_imports(site: &std::path::Path) -> Result<Vec<String>> {
+    let mut result = vec![];
+    if !site.is_dir() {
+        return Ok(result);
+    }
+    for entry in std::fs::read_dir(site)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().and_then(|i| i.to_str()) != Some("pth") {
+            continue;
+        }
+        let content = String::from_utf8_lossy(&std::fs::read(&path)?).to_string();
+        for line in content.lines() {
+            if !line.starts_with("import ") && !line.starts_with("import\t") {
+                continue;
+            }
+            for statement in line.split(';').map(|i| i.trim()) {
+                let names = statement
+                    .strip_prefix("import ")
+                    .or_else(|| statement.strip_prefix("import\t"));
+                if let Some(names) = names {
+                    for name in names.split(',') {
+                        let name = name.split_whitespace().next().unwrap_or_default();
+                        if !name.is_empty() {
+                            result.push(name.to_string());
+                        }
+                    }
+                } else if let Some(rest) = statement.strip_prefix("from ") {
+                    if let Some(name) = rest.split_whitespace().next() {
+                        result.push(name.to_string());
+                    }
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+// 目录中是否有扩展模块
+fn has_extensions(dir: &std::path::Path) -> Result<bool> {
+    for i in std::fs::read_dir(dir)? {
+        let i = i?;
+        if i.file_type()?.is_dir() {
+            if has_extensions(&i.path())? {
+                return Ok(true);
+            }
+        } else if i
+            .file_name()
+            .to_string_lossy()
+            .to_ascii_lowercase()
+            .ends_with(".pyd")
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|i| i.is_alphabetic() || i == '_')
+        && chars.all(|i| i.is_alphanumeric() || i == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pth_and_extensions() {
+        let site = std::env::temp_dir().join(format!("pyembed-shake-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&site);
+        let write = |path: &str, content: &str| {
+            let path = site.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("pywin32.pth", "win32\nimport pywin32_bootstrap\n");
+        write(
+            "demo-nspkg.pth",
+            "import sys, types, os;has_mfs = sys.version_info > (3, 5)\n",
+        );
+        write("pywin32_bootstrap.py", "");
+        write("unused.py", "");
+        write("native/__init__.py", "");
+        write("native/_core.pyd", "");
+        write("native/helper.py", "");
+        write("pure/__init__.py", "");
+        write("pure/helper.py", "");
+
+        let mut imports = pth_imports(&site).unwrap();
+        imports.sort();
+        assert_eq!(imports, ["os", "pywin32_bootstrap", "sys", "types"]);
+
+        let mut shaker = Shaker {
+            entry_script: "app.py".into(),
+            mode: "static",
+            modules: ["native", "pure"].iter().map(|i| i.to_string()).collect(),
+            missing: vec![],
+            keep: vec![],
+            removed_site_packages: vec![],
+            removed_stdlib: vec![],
+            removed_bytes: 0,
+        };
+        shaker.modules.extend(imports);
+        shaker.walk(&site, "", &[]).unwrap();
+        assert!(site.join("pywin32_bootstrap.py").exists());
+        assert!(!site.join("unused.py").exists());
+        // 含有扩展模块的包整个保留
+        assert!(site.join("native").join("helper.py").exists());
+        assert!(!site.join("pure").join("helper.py").exists());
+        assert!(site.join("pure").join("__init__.py").exists());
+        std::fs::remove_dir_all(&site).unwrap();
+    }
+}
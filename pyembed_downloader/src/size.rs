@@ -0,0 +1,161 @@
+// 运行时的磁盘占用，在编译和清理前后各统计一次
+// 每个包按 RECORD 中的顶层文件和目录统计，清理时可能删除 dist-info，因此事先读取
+// 打包到 site-packages.zip 中的文件按压缩后的大小统计，多个包共用的顶层目录（命名空间包）会重复统计
+
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+pub struct Size {
+    pub before: u64,
+    pub after: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DistributionSize {
+    pub name: String,
+    pub version: String,
+    pub size: Size,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SizeReport {
+    // 整个运行时目录
+    pub total: Size,
+    // 标准库 pythonXY.zip
+    pub stdlib_zip: Size,
+    // 按清理后的大小从大到小排列
+    pub distributions: Vec<DistributionSize>,
+}
+
+pub(crate) struct Tracker {
+    report: SizeReport,
+    // 与 report.distributions 对应的 dist-info 和顶层文件、目录
+    entries: Vec<Vec<String>>,
+}
+
+impl Tracker {
+    pub(crate) fn new(dir: &std::path::Path, pyver: &str) -> Result<Tracker> {
+        let site = dir.join("Lib").join("site-packages");
+        let mut distributions = vec![];
+        let mut entries = vec![];
+        for (dist_info, metadata) in crate::metadata::installed(&site)? {
+            let mut names = vec![dist_info.file_name().unwrap().to_string_lossy().to_string()];
+            for i in crate::modify::top_level(&dist_info)? {
+                if i == "__pycache__" {
+                    continue;
+                }
+                // 编译后顶层的 .py 变为 .pyc
+                if let Some(stem) = i.strip_suffix(".py") {
+                    names.push(format!("{}.pyc", stem));
+                }
+                names.push(i);
+            }
+            distributions.push(DistributionSize {
+                name: metadata.name,
+                version: metadata.version,
+                size: Size {
+                    before: usage(&site, &names, &Default::default())?,
+                    after: 0,
+                },
+            });
+            entries.push(names);
+        }
+        let report = SizeReport {
+            total: Size {
+                before: crate::utility::disk_usage(dir)?.1,
+                after: 0,
+            },
+            stdlib_zip: Size {
+                before: file_size(&stdlib_zip(dir, pyver))?,
+                after: 0,
+            },
+            distributions,
+        };
+        Ok(Tracker { report, entries })
+    }
+
+    pub(crate) fn finish(mut self, dir: &std::path::Path, pyver: &str) -> Result<SizeReport> {
+        let site = dir.join("Lib").join("site-packages");
+        let zipped = zipped(&dir.join("Lib").join(crate::zipsite::ZIP_NAME))?;
+        for (distribution, names) in self.report.distributions.iter_mut().zip(self.entries) {
+            distribution.size.after = usage(&site, &names, &zipped)?;
+        }
+        self.report.total.after = crate::utility::disk_usage(dir)?.1;
+        self.report.stdlib_zip.after = file_size(&stdlib_zip(dir, pyver))?;
+        self.report
+            .distributions
+            .sort_by(|a, b| b.size.after.cmp(&a.size.after).then(a.name.cmp(&b.name)));
+        Ok(self.report)
+    }
+}
+
+fn stdlib_zip(dir: &std::path::Path, pyver: &str) -> std::path::PathBuf {
+    dir.join(format!("python{}.zip", crate::utility::python_xy(pyver)))
+}
+
+fn file_size(path: &std::path::Path) -> Result<u64> {
+    match std::fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// site-packages.zip 中按顶层名称统计的压缩后大小
+fn zipped(path: &std::path::Path) -> Result<std::collections::HashMap<String, u64>> {
+    let mut result = std::collections::HashMap::new();
+    if !path.exists() {
+        return Ok(result);
+    }
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        let top = file
+            .name()
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        *result.entry(top).or_insert(0) += file.compressed_size();
+    }
+    Ok(result)
+}
+
+fn usage(
+    site: &std::path::Path,
+    names: &[String],
+    zipped: &std::collections::HashMap<String, u64>,
+) -> Result<u64> {
+    let mut bytes = 0;
+    for name in names {
+        let path = site.join(name);
+        if path.exists() {
+            bytes += crate::utility::disk_usage(&path)?.1;
+        }
+        bytes += zipped.get(name).copied().unwrap_or_default();
+    }
+    Ok(bytes)
+}
+
+// 按列对齐的大小表格，每个包一行，最后是标准库和总计
+pub fn size_table(report: &SizeReport) -> Vec<String> {
+    let format = |size: &Size| {
+        [
+            crate::utility::format_size(size.before),
+            crate::utility::format_size(size.after),
+        ]
+    };
+    let mut rows: Vec<[String; 4]> = vec![];
+    for i in report.distributions.iter() {
+        let [before, after] = format(&i.size);
+        rows.push([i.name.clone(), i.version.clone(), before, after]);
+    }
+    for (name, size) in [
+        ("(stdlib zip)", &report.stdlib_zip),
+        ("(total)", &report.total),
+    ] {
+        let [before, after] = format(size);
+        rows.push([name.to_string(), String::new(), before, after]);
+    }
+    crate::report::align(["Package", "Version", "Before", "After"], rows)
+}
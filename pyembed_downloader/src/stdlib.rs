@@ -0,0 +1,197 @@
+// 按 optimize 重新编译嵌入式包中的 pythonXY.zip
+// 嵌入式包中只有 .pyc，源文件取自同版本的源码包 Python-x.x.x.tgz
+// 原始的 pythonXY.zip 保存在缓存目录中，每次都从原始文件重新生成
+
+use crate::config::{Backend, Config};
+use crate::Result;
+
+const SCRIPT: &str = r#"
+import sys
+import json
+import tarfile
+import zipfile
+import marshal
+import importlib.util
+
+ORIGINAL, SOURCE, OUTPUT = sys.argv[1:4]
+OPTIMIZE = int(sys.argv[4])
+INVALIDATION_MODE = sys.argv[5]
+
+with tarfile.open(SOURCE) as tar:
+    # Python-x.x.x/Lib/os.py -> os.py
+    sources = {}
+    for member in tar.getmembers():
+        parts = member.name.split("/", 2)
+        if len(parts) == 3 and parts[1] == "Lib" and member.isfile() and parts[2].endswith(".py"):
+            sources[parts[2]] = member
+
+    compiled = 0
+    kept = 0
+    with zipfile.ZipFile(ORIGINAL) as original, zipfile.ZipFile(OUTPUT, "w") as output:
+        for info in sorted(original.infolist(), key=lambda i: i.filename):
+            data = original.read(info)
+            member = sources.get(info.filename[:-1]) if info.filename.endswith(".pyc") else None
+            if member is not None:
+                source = tar.extractfile(member).read()
+                code = compile(source, info.filename[:-1], "exec", dont_inherit=True, optimize=OPTIMIZE)
+                # .pyc 头部（PEP 552）：magic、flags，之后是修改时间和源文件大小，或者源文件的哈希
+                if INVALIDATION_MODE == "timestamp":
+                    header = (0).to_bytes(4, "little") + (0).to_bytes(4, "little")
+                    header += (len(source) & 0xFFFFFFFF).to_bytes(4, "little")
+                else:
+                    flags = 0b11 if INVALIDATION_MODE == "checked-hash" else 0b01
+                    header = flags.to_bytes(4, "little") + importlib.util.source_hash(source)
+                data = importlib.util.MAGIC_NUMBER + header + marshal.dumps(code)
+                compiled += 1
+            else:
+                kept += 1
+            # 保留原来的时间，使用固定的压缩方式，相同的输入得到相同的 zip
+            item = zipfile.ZipInfo(info.filename, date_time=info.date_time)
+            item.compress_type = zipfile.ZIP_DEFLATED
+            item.external_attr = info.external_attr
+            output.writestr(item, data, compresslevel=9)
+
+print(json.dumps({"compiled": compiled, "kept": kept}))
+"#;
+
+fn source_path(cachedir: &std::path::Path, pyver: &str) -> std::path::PathBuf {
+    cachedir.join(format!("Python-{}.tgz", pyver))
+}
+
+// 下载源码包到缓存目录，已存在时跳过
+pub(crate) async fn download_source(
+    config: &Config,
+    cachedir: &std::path::Path,
+    pyver: &str,
+    progress_callback: &dyn Fn(i64, i64),
+) -> Result<std::path::PathBuf> {
+    let path = source_path(cachedir, pyver);
+    if config.no_index && path.exists() {
+        info!("离线模式，使用缓存中的 {}", path.display());
+        return Ok(path);
+    }
+
+    warn!("正在获取源码包下载信息 ...");
+    let page = format!(
+        "https://www.python.org/downloads/release/python-{}/",
+        pyver.replace('.', "")
+    );
+    let body = crate::get(&page).await?;
+    let manual = format!(
+        "可以手动下载源码包保存为 {} 后以离线模式运行",
+        path.display()
+    );
+    let caps =
+        crate::utility::regex_find(r#""([^"]*?\.tgz)">Gzipped source tarball(.*?)</tr>"#, &body)
+            .ok_or_else(|| format!("在 {} 中找不到源码包信息，{}", page, manual))?;
+    let mut url = caps.get(1).unwrap().as_str().to_string();
+    // 优先使用 SHA-256，只提供 MD5 的页面使用 MD5
+    let row = caps.get(2).unwrap().as_str();
+    let (digest, hash): (fn(&[u8]) -> String, String) =
+        match crate::utility::regex_find(r"\b([a-fA-F0-9]{64})\b", row) {
+            Some(caps) => (crate::utility::sha256_hex, caps[1].to_string()),
+            None => match crate::utility::regex_find(r"\b([a-fA-F0-9]{32})\b", row) {
+                Some(caps) => {
+                    warn!("发布页面只提供了源码包的 MD5，使用 MD5 校验");
+                    (
+                        |data| format!("{:x}", md5::compute(data)),
+                        caps[1].to_string(),
+                    )
+                }
+                None => return Err(format!("在 {} 中找不到源码包的哈希，{}", page, manual).into()),
+            },
+        };
+    if !config.python_mirror.is_empty() {
+        url = url.replace(
+            "https://www.python.org/ftp/python",
+            config.python_mirror.trim_end_matches('/'),
+        );
+    }
+    info!("下载链接：{}", url);
+    info!("文件哈希：{}", hash);
+
+    if let Ok(data) = std::fs::read(&path) {
+        if digest(&data).eq_ignore_ascii_case(&hash) {
+            info!("文件已存在，跳过下载");
+            return Ok(path);
+        }
+    }
+    warn!("正在下载源码包 ...");
+    let data = crate::download_progress(&url, progress_callback).await?;
+    progress_callback(-1, -1);
+    let actual = digest(&data);
+    if !actual.eq_ignore_ascii_case(&hash) {
+        info!("预期：{}", hash);
+        info!("实际：{}", actual);
+        return Err("源码包哈希不匹配".into());
+    }
+    std::fs::write(&path, &data)?;
+    Ok(path)
+}
+
+// 需要先调用 download_source
+pub(crate) fn recompile(config: &Config, dir: &std::path::Path, pyver: &str) -> Result<()> {
+    let mut cachedir = config.cache_dir.clone();
+    if cachedir.is_relative() {
+        cachedir = std::env::current_dir()?.join(cachedir);
+    }
+    let source = source_path(&cachedir, pyver);
+    if !source.exists() {
+        return Err(format!("找不到源码包：{}", source.display()).into());
+    }
+
+    let name = format!("python{}.zip", crate::utility::python_xy(pyver));
+    let zippath = dir.join(&name);
+    // 原始文件按版本和架构保存，已经保存过时运行时中的可能是重新编译过的
+    let arch = if config.is32 { "x86" } else { "amd64" };
+    let original = cachedir
+        .join("pyembed-stdlib")
+        .join(format!("{}-{}", pyver, arch))
+        .join(&name);
+    if !original.exists() {
+        if !zippath.exists() {
+            return Err(format!("找不到 {}", zippath.display()).into());
+        }
+        std::fs::create_dir_all(original.parent().unwrap())?;
+        std::fs::copy(&zippath, &original)?;
+        info!("原始文件已保存到：{}", original.display());
+    }
+
+    // 编译使用的 Python 次版本必须与目标相同
+    let mut cmd = if config.installer == Backend::Native || !cfg!(windows) {
+        // 要求了重新编译却无法编译时报错，不使用原始的 pythonXY.zip 继续构建
+        if !crate::host_python_matches(config, pyver) {
+            return Err(format!(
+                "重新编译 {} 需要与目标次版本相同的本机 Python，可以用 --host-python 指定",
+                name
+            )
+            .into());
+        }
+        crate::new_host_python_command(config)
+    } else {
+        crate::new_python_command(dir)
+    };
+    // 运行目标 python.exe 时会读取 pythonXY.zip，先输出到临时文件
+    let output = dir.join(format!("{}.tmp", name));
+    cmd.args(["-c", SCRIPT]);
+    cmd.arg(&original);
+    cmd.arg(&source);
+    cmd.arg(&output);
+    cmd.arg(config.optimize.to_string());
+    cmd.arg(config.invalidation_mode.as_str());
+    let result = cmd.output()?;
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&output);
+        for line in String::from_utf8_lossy(&result.stderr).lines() {
+            error!("{}", line);
+        }
+        return Err(format!("重新编译 {} 失败 [{}]", name, result.status).into());
+    }
+    let summary: serde_json::Value = serde_json::from_slice(&result.stdout)?;
+    std::fs::rename(&output, &zippath)?;
+    info!(
+        "重新编译 {}：编译 {} 个文件，保留 {} 个文件",
+        name, summary["compiled"], summary["kept"]
+    );
+    Ok(())
+}
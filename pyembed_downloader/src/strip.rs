@@ -0,0 +1,238 @@
+// 编译后按规则删除 site-packages 中不需要的文件，如测试、文档、类型存根、C 源文件
+// 规则与 .gitignore 相同：
+// 以 / 结尾只匹配目录，以 ! 开头表示重新保留，最后一个匹配的规则生效
+// 不含 / 的规则匹配任意层级的文件名，含 / 的规则匹配相对路径
+// [包名] 之后的规则只作用于该包，路径相对于包的顶层目录，[*] 恢复为作用于所有包
+// 删除的目录不再检查其中的文件，因此不能用 ! 保留已删除目录中的文件
+
+use crate::config::Config;
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+// 内置规则，设置 strip 时使用，用户规则在其后，可以用 ! 覆盖
+const DEFAULT_RULES: &str = "
+tests/
+test/
+docs/
+doc/
+examples/
+*.pyi
+*.pxd
+*.pyx
+*.c
+*.h
+*.cpp
+*.hpp
+";
+
+struct Rule {
+    // 原始文本，用于输出日志
+    text: String,
+    matcher: Matcher,
+    negate: bool,
+    dir_only: bool,
+    // 规范化后的包名，为 None 时作用于所有包
+    package: Option<String>,
+    removed_files: u64,
+    removed_bytes: u64,
+}
+
+enum Matcher {
+    Glob(globset::GlobMatcher),
+    // __pycache__ 中其他 Python 版本的 .pyc，参数为目标版本的标签，如 cpython-311
+    OtherPycache(String),
+}
+
+impl Rule {
+    fn parse(line: &str, package: Option<&str>) -> Result<Option<Rule>> {
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            return Ok(None);
+        }
+        let (negate, pattern) = match text.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, text),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let pattern = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let glob = globset::GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("无效的删除规则 {}：{}", text, e))?;
+        Ok(Some(Rule {
+            text: match package {
+                Some(package) => format!("[{}] {}", package, text),
+                None => text.to_string(),
+            },
+            matcher: Matcher::Glob(glob.compile_matcher()),
+            negate,
+            dir_only,
+            package: package.map(normalize_name),
+            removed_files: 0,
+            removed_bytes: 0,
+        }))
+    }
+
+    // path 为相对于规则作用范围的路径
+    fn is_match(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        match &self.matcher {
+            Matcher::Glob(glob) => glob.is_match(path),
+            Matcher::OtherPycache(tag) => {
+                let mut parts = path.rsplit('/');
+                let name = parts.next().unwrap_or_default();
+                !is_dir
+                    && parts.next() == Some("__pycache__")
+                    && name.ends_with(".pyc")
+                    && !name.contains(&format!(".{}", tag))
+            }
+        }
+    }
+}
+
+fn parse_rules(content: &str, rules: &mut Vec<Rule>) -> Result<()> {
+    let mut package: Option<String> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|i| i.strip_suffix(']')) {
+            package = match name.trim() {
+                "*" => None,
+                name => Some(name.to_string()),
+            };
+            continue;
+        }
+        if let Some(rule) = Rule::parse(line, package.as_deref())? {
+            rules.push(rule);
+        }
+    }
+    Ok(())
+}
+
+// paths 为 site-packages 中要处理的顶层文件和目录，为空时处理全部
+pub(crate) fn strip(
+    config: &Config,
+    dir: &std::path::Path,
+    pyver: &str,
+    paths: &[String],
+) -> Result<()> {
+    let mut rules = vec![];
+    if config.strip {
+        parse_rules(DEFAULT_RULES, &mut rules)?;
+        let tag = format!("cpython-{}", crate::utility::python_xy(pyver));
+        rules.push(Rule {
+            text: "__pycache__ 中其他版本的 .pyc".to_string(),
+            matcher: Matcher::OtherPycache(tag),
+            negate: false,
+            dir_only: false,
+            package: None,
+            removed_files: 0,
+            removed_bytes: 0,
+        });
+    }
+    if let Some(path) = &config.strip_rules {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("无法读取删除规则文件 {}：{}", path.display(), e))?;
+        parse_rules(&content, &mut rules)?;
+    }
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let site = dir.join("Lib").join("site-packages");
+    // 顶层文件和目录所属的包
+    let mut owners: Vec<(String, String)> = vec![];
+    for (dist_info, metadata) in crate::metadata::installed(&site)? {
+        for top in crate::modify::top_level(&dist_info)? {
+            owners.push((top, normalize_name(&metadata.name)));
+        }
+    }
+    walk(&site, "", paths, &owners, &mut rules)?;
+
+    for i in rules.iter().filter(|i| i.removed_files > 0) {
+        info!(
+            "{}：删除 {} 个文件，{}",
+            i.text,
+            i.removed_files,
+            crate::utility::format_size(i.removed_bytes)
+        );
+    }
+    let total: u64 = rules.iter().map(|i| i.removed_bytes).sum();
+    info!("共删除 {}", crate::utility::format_size(total));
+    Ok(())
+}
+
+fn walk(
+    site: &std::path::Path,
+    dir: &str,
+    paths: &[String],
+    owners: &[(String, String)],
+    rules: &mut [Rule],
+) -> Result<()> {
+    let mut entries: Vec<std::fs::DirEntry> =
+        std::fs::read_dir(site.join(dir))?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|i| i.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = if dir.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", dir, name)
+        };
+        let is_dir = entry.file_type()?.is_dir();
+        // 不处理元数据目录
+        if dir.is_empty() && (name.ends_with(".dist-info") || name.ends_with(".egg-info")) {
+            continue;
+        }
+        if dir.is_empty() && !paths.is_empty() && !paths.contains(&name) {
+            continue;
+        }
+        let top = path.split('/').next().unwrap_or_default();
+        let mut matched = None;
+        for (n, rule) in rules.iter().enumerate() {
+            let relative = match &rule.package {
+                None => path.as_str(),
+                Some(package) => {
+                    if !owners.iter().any(|(t, p)| t == top && p == package) {
+                        continue;
+                    }
+                    match path.split_once('/') {
+                        Some((_, relative)) => relative,
+                        // 顶层的模块文件或目录本身
+                        None => name.as_str(),
+                    }
+                }
+            };
+            if rule.is_match(relative, is_dir) {
+                matched = Some(n);
+            }
+        }
+        match matched {
+            Some(n) if !rules[n].negate => {
+                let fullpath = entry.path();
+                let (files, bytes) = crate::utility::disk_usage(&fullpath)?;
+                if is_dir {
+                    std::fs::remove_dir_all(&fullpath)?;
+                } else {
+                    std::fs::remove_file(&fullpath)?;
+                }
+                rules[n].removed_files += files;
+                rules[n].removed_bytes += bytes;
+            }
+            _ => {
+                if is_dir {
+                    walk(site, &path, paths, owners, rules)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -2,6 +2,7 @@ use crate::Result;
 
 // https://github.com/rust-lang/cargo/blob/master/src/cargo/util/job.rs
 // 简单一抄，凑合能用
+#[cfg(windows)]
 pub(crate) fn setup_job() -> Result<Job> {
     unsafe {
         use winapi::shared::minwindef::*;
@@ -35,10 +36,12 @@ pub(crate) fn setup_job() -> Result<Job> {
     }
 }
 
+#[cfg(windows)]
 pub struct Job {
     handle: Handle,
 }
 
+#[cfg(windows)]
 impl Drop for Job {
     fn drop(&mut self) {
         unsafe {
@@ -60,10 +63,12 @@ impl Drop for Job {
     }
 }
 
+#[cfg(windows)]
 pub struct Handle {
     inner: winapi::um::winnt::HANDLE,
 }
 
+#[cfg(windows)]
 impl Drop for Handle {
     fn drop(&mut self) {
         unsafe {
@@ -72,6 +77,15 @@ impl Drop for Handle {
     }
 }
 
+// 在其他系统上只用于交叉构建，不会启动 Windows 程序，不需要任务对象
+#[cfg(not(windows))]
+pub(crate) fn setup_job() -> Result<Job> {
+    Ok(Job)
+}
+
+#[cfg(not(windows))]
+pub struct Job;
+
 pub(crate) fn regex_find<'a>(re: &str, text: &'a str) -> Option<regex::Captures<'a>> {
     if let Ok(re) = regex::RegexBuilder::new(re)
         .dot_matches_new_line(true)
@@ -81,3 +95,121 @@ pub(crate) fn regex_find<'a>(re: &str, text: &'a str) -> Option<regex::Captures<
     }
     None
 }
+
+// 主版本号和次版本号连在一起，如 3.11.4 -> 311，用于 python311.zip、python311._pth 等文件名
+// pyver 应当已经检查过格式，缺少的部分按空处理，不会 panic
+pub(crate) fn python_xy(pyver: &str) -> String {
+    pyver.split('.').take(2).collect()
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    use sha2::Digest;
+    format!("{:x}", sha2::Sha256::digest(data))
+}
+
+// 读取 PE 文件中 VS_FIXEDFILEINFO 记录的文件版本，不需要运行该程序
+pub(crate) fn pe_file_version(data: &[u8]) -> Option<(u16, u16, u16, u16)> {
+    const SIGNATURE: [u8; 4] = [0xBD, 0x04, 0xEF, 0xFE];
+    let pos = data.windows(4).position(|i| i == SIGNATURE)?;
+    // dwSignature、dwStrucVersion 之后是 dwFileVersionMS、dwFileVersionLS
+    let info = data.get(pos + 8..pos + 16)?;
+    let ms = u32::from_le_bytes(info[0..4].try_into().ok()?);
+    let ls = u32::from_le_bytes(info[4..8].try_into().ok()?);
+    Some((
+        (ms >> 16) as u16,
+        (ms & 0xFFFF) as u16,
+        (ls >> 16) as u16,
+        (ls & 0xFFFF) as u16,
+    ))
+}
+
+// 递归复制目录，target 不存在时创建
+pub(crate) fn copy_dir(source: &std::path::Path, target: &std::path::Path) -> Result<()> {
+    std::fs::create_dir_all(target)?;
+    for i in std::fs::read_dir(source)? {
+        let i = i?;
+        let path = i.path();
+        let dest = target.join(i.file_name());
+        if i.file_type()?.is_dir() {
+            copy_dir(&path, &dest)?;
+        } else {
+            std::fs::copy(&path, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+// 把 dir 中所有文件的修改时间限制为不晚于 epoch（SOURCE_DATE_EPOCH），用于可重现构建
+pub(crate) fn clamp_mtime(dir: &std::path::Path, epoch: u64) -> Result<()> {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(epoch);
+    for i in std::fs::read_dir(dir)? {
+        let i = i?;
+        let path = i.path();
+        if i.file_type()?.is_dir() {
+            clamp_mtime(&path, epoch)?;
+        } else if i.metadata()?.modified()? > time {
+            let file = std::fs::OpenOptions::new().write(true).open(&path)?;
+            file.set_modified(time)?;
+        }
+    }
+    Ok(())
+}
+
+// epoch 对应的 UTC 时间，zip 只能表示 1980 年到 2107 年之间的时间
+pub(crate) fn zip_datetime(epoch: u64) -> zip::DateTime {
+    let days = (epoch / 86400) as i64;
+    let secs = epoch % 86400;
+    // 按公历从天数计算日期
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    if year < 1980 {
+        return zip::DateTime::default();
+    }
+    zip::DateTime::from_date_and_time(
+        year.min(2107) as u16,
+        month,
+        day,
+        (secs / 3600) as u8,
+        (secs % 3600 / 60) as u8,
+        (secs % 60) as u8,
+    )
+    .unwrap_or_default()
+}
+
+// 便于阅读的文件大小，如 1.5 MB
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// 文件或目录中的文件数量和总大小
+pub(crate) fn disk_usage(path: &std::path::Path) -> Result<(u64, u64)> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok((1, metadata.len()));
+    }
+    let mut result = (0, 0);
+    for i in std::fs::read_dir(path)? {
+        let (files, bytes) = disk_usage(&i?.path())?;
+        result.0 += files;
+        result.1 += bytes;
+    }
+    Ok(result)
+}
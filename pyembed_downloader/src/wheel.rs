@@ -0,0 +1,557 @@
+// 内置的 wheel 安装器，不需要运行目标 python.exe，可以在非 Windows 系统上构建运行时
+// 只支持 wheel，不支持源码包；不会为 console_scripts 生成 exe 启动器
+
+use crate::metadata::Metadata;
+use crate::pep440::Version;
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+const INSTALLER: &str = "pyembed_downloader";
+
+// wheel 文件名：{name}-{ver}(-{build})?-{python}-{abi}-{platform}.whl
+#[derive(Debug, Clone)]
+pub(crate) struct WheelName {
+    // 规范化后的名称
+    pub name: String,
+    pub version: Version,
+    pub python: Vec<String>,
+    pub abi: Vec<String>,
+    pub platform: Vec<String>,
+}
+
+impl std::str::FromStr for WheelName {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<WheelName> {
+        let err = || format!("无效的 wheel 文件名：{}", s);
+        let stem = match s.strip_suffix(".whl") {
+            Some(stem) => stem,
+            None => return Err(err().into()),
+        };
+        let parts: Vec<&str> = stem.split('-').collect();
+        if parts.len() != 5 && parts.len() != 6 {
+            return Err(err().into());
+        }
+        let n = parts.len();
+        let split = |s: &str| s.split('.').map(|i| i.to_ascii_lowercase()).collect();
+        Ok(WheelName {
+            name: normalize_name(parts[0]),
+            version: parts[1].parse().map_err(|_| err())?,
+            python: split(parts[n - 3]),
+            abi: split(parts[n - 2]),
+            platform: split(parts[n - 1]),
+        })
+    }
+}
+
+impl WheelName {
+    // 与目标运行时兼容时返回优先级，数值越大越优先，不兼容时返回 None
+    pub fn score(&self, python: &str, is32: bool) -> Option<u32> {
+        let v: Vec<&str> = python.split('.').collect();
+        if v.len() < 2 {
+            return None;
+        }
+        let (major, minor) = (v[0], v[1].parse::<u32>().ok()?);
+        let cp = format!("cp{}{}", major, minor);
+        let platform = if is32 { "win32" } else { "win_amd64" };
+
+        let platform_score = self
+            .platform
+            .iter()
+            .filter_map(|i| match i.as_str() {
+                i if i == platform => Some(2),
+                "any" => Some(1),
+                _ => None,
+            })
+            .max()?;
+        let abi_score = self
+            .abi
+            .iter()
+            .filter_map(|i| match i.as_str() {
+                i if i == cp => Some(3),
+                "abi3" => Some(2),
+                "none" => Some(1),
+                _ => None,
+            })
+            .max()?;
+        let python_score = self
+            .python
+            .iter()
+            .filter_map(|i| {
+                if *i == cp {
+                    return Some(3);
+                }
+                // abi3 的 wheel 可用于更高的次版本，如 cp38-abi3 可用于 3.11
+                if let Some(n) = i.strip_prefix(&format!("cp{}", major)) {
+                    if abi_score == 2 && n.parse::<u32>().ok()? <= minor {
+                        return Some(2);
+                    }
+                    return None;
+                }
+                // py3、py311、py2.py3
+                let n = i.strip_prefix("py")?;
+                if n == major || n == format!("{}{}", major, minor) {
+                    return Some(1);
+                }
+                None
+            })
+            .max()?;
+        // py3-none-win_amd64 之类的组合是允许的，但 cp311-cp311-any 不合法
+        if abi_score == 3 && python_score != 3 {
+            return None;
+        }
+        Some(platform_score * 100 + abi_score * 10 + python_score)
+    }
+}
+
+// 读取 wheel 中的 METADATA
+pub(crate) fn read_metadata(wheel: &std::path::Path) -> Result<Metadata> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(wheel)?)?;
+    let dist_info = find_dist_info(&mut zip, wheel)?;
+    let mut item = zip.by_name(&format!("{}/METADATA", dist_info))?;
+    let mut data = vec![];
+    std::io::Read::read_to_end(&mut item, &mut data)?;
+    Ok(crate::metadata::parse(&String::from_utf8_lossy(&data)))
+}
+
+fn find_dist_info(
+    zip: &mut zip::ZipArchive<std::fs::File>,
+    wheel: &std::path::Path,
+) -> Result<String> {
+    let mut dirs: Vec<String> = zip
+        .file_names()
+        .filter_map(|i| i.split_once('/').map(|i| i.0))
+        .filter(|i| i.ends_with(".dist-info"))
+        .map(|i| i.to_string())
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    if dirs.len() != 1 {
+        return Err(format!("{} 中应该有且只有一个 dist-info 目录", wheel.display()).into());
+    }
+    Ok(dirs.remove(0))
+}
+
+// 把 wheel 安装到 root（运行时目录）中，已安装的同名包会先被卸载
+// requested 表示是否为用户直接请求的包，用于写入 REQUESTED
+pub(crate) fn install(
+    wheel: &std::path::Path,
+    root: &std::path::Path,
+    requested: bool,
+) -> Result<Metadata> {
+    let site = root.join("Lib").join("site-packages");
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(wheel)?)?;
+    let dist_info = find_dist_info(&mut zip, wheel)?;
+    let data_dir = format!("{}.data", dist_info.trim_end_matches(".dist-info"));
+
+    let read = |zip: &mut zip::ZipArchive<std::fs::File>, name: &str| -> Result<Vec<u8>> {
+        let mut item = zip.by_name(name)?;
+        let mut data = vec![];
+        std::io::Read::read_to_end(&mut item, &mut data)?;
+        Ok(data)
+    };
+    let wheel_info = String::from_utf8(read(&mut zip, &format!("{}/WHEEL", dist_info))?)?;
+    let wheel_version = wheel_info
+        .lines()
+        .find_map(|i| i.strip_prefix("Wheel-Version:"))
+        .map(|i| i.trim().to_string())
+        .unwrap_or_default();
+    if !wheel_version.starts_with("1.") {
+        return Err(format!(
+            "{} 的 Wheel-Version 不受支持：{}",
+            wheel.display(),
+            wheel_version
+        )
+        .into());
+    }
+    let metadata = crate::metadata::parse(&String::from_utf8_lossy(&read(
+        &mut zip,
+        &format!("{}/METADATA", dist_info),
+    )?));
+
+    // wheel 中的 RECORD 记录了每个文件的哈希，解压时逐个校验
+    let record = String::from_utf8(read(&mut zip, &format!("{}/RECORD", dist_info))?)?;
+    let mut expected = std::collections::HashMap::new();
+    for line in record.lines() {
+        let fields = parse_record_line(line);
+        if fields.len() >= 2 && !fields[1].is_empty() {
+            expected.insert(fields[0].clone(), fields[1].clone());
+        }
+    }
+
+    if let Some((old, _)) = crate::metadata::find(&site, &metadata.name)? {
+        info!("卸载旧版本：{}", old.file_name().unwrap().to_string_lossy());
+        uninstall(root, &metadata.name)?;
+    }
+
+    info!("安装：{}", wheel.file_name().unwrap().to_string_lossy());
+    let mut installed: Vec<(String, String, usize)> = vec![];
+    for i in 0..zip.len() {
+        let mut item = zip.by_index(i)?;
+        if item.is_dir() {
+            continue;
+        }
+        let name = item.name().to_string();
+        if name == format!("{}/RECORD", dist_info)
+            || name.starts_with(&format!("{}/RECORD.", dist_info))
+        {
+            continue;
+        }
+        let target = match target_path(&name, &data_dir, root, &site, &metadata.name)? {
+            Some(target) => target,
+            None => continue,
+        };
+        let mut data = vec![];
+        std::io::Read::read_to_end(&mut item, &mut data)?;
+        let hash = record_hash(&data);
+        if let Some(h) = expected.get(&name) {
+            if h.starts_with("sha256=") && *h != hash {
+                return Err(format!("{} 中 {} 的哈希不匹配", wheel.display(), name).into());
+            }
+        }
+        std::fs::create_dir_all(target.parent().unwrap())?;
+        std::fs::write(&target, &data)?;
+        installed.push((record_path(&target, &site)?, hash, data.len()));
+    }
+
+    let dist_info_dir = site.join(&dist_info);
+    let mut extra_files = vec![("INSTALLER", format!("{}\n", INSTALLER))];
+    if requested {
+        extra_files.push(("REQUESTED", String::new()));
+    }
+    for (name, content) in extra_files {
+        let path = dist_info_dir.join(name);
+        std::fs::write(&path, &content)?;
+        installed.push((
+            record_path(&path, &site)?,
+            record_hash(content.as_bytes()),
+            content.len(),
+        ));
+    }
+    let mut content = String::new();
+    for (path, hash, size) in installed {
+        content += &format!("{},{},{}\n", record_field(&path), hash, size);
+    }
+    content += &format!("{}/RECORD,,\n", dist_info);
+    std::fs::write(dist_info_dir.join("RECORD"), content)?;
+    Ok(metadata)
+}
+
+// 计算 wheel 中的文件在运行时中的位置，.data 目录中的文件按 scheme 安装
+fn target_path(
+    name: &str,
+    data_dir: &str,
+    root: &std::path::Path,
+    site: &std::path::Path,
+    project: &str,
+) -> Result<Option<std::path::PathBuf>> {
+    let parts: Vec<&str> = name.split('/').collect();
+    if parts
+        .iter()
+        .any(|i| i.is_empty() || *i == "." || *i == ".." || i.contains(['\\', ':']))
+    {
+        return Err(format!("wheel 中含有不安全的路径：{}", name).into());
+    }
+    if parts[0] != data_dir {
+        return Ok(Some(
+            parts.iter().fold(site.to_path_buf(), |p, i| p.join(i)),
+        ));
+    }
+    if parts.len() < 3 {
+        return Ok(None);
+    }
+    // 嵌入式运行时的目录布局与 Windows 上的标准安装一致
+    let base = match parts[1] {
+        "purelib" | "platlib" => site.to_path_buf(),
+        "scripts" => root.join("Scripts"),
+        "headers" => root.join("Include").join(project),
+        "data" => root.to_path_buf(),
+        scheme => return Err(format!("wheel 中含有未知的安装位置：{}", scheme).into()),
+    };
+    Ok(Some(parts[2..].iter().fold(base, |p, i| p.join(i))))
+}
+
+// RECORD 中的路径相对于 site-packages，使用 / 分隔
+fn record_path(path: &std::path::Path, site: &std::path::Path) -> Result<String> {
+    if let Ok(p) = path.strip_prefix(site) {
+        let parts: Vec<String> = p
+            .components()
+            .map(|i| i.as_os_str().to_string_lossy().to_string())
+            .collect();
+        return Ok(parts.join("/"));
+    }
+    // site-packages 之外的文件，如 Scripts 目录，site-packages 位于 <root>/Lib/site-packages
+    let root = site.parent().unwrap().parent().unwrap();
+    match path.strip_prefix(root) {
+        Ok(p) => {
+            let mut parts = vec!["..".to_string(), "..".to_string()];
+            parts.extend(
+                p.components()
+                    .map(|i| i.as_os_str().to_string_lossy().to_string()),
+            );
+            Ok(parts.join("/"))
+        }
+        Err(_) => Err(format!("{} 不在运行时目录中", path.display()).into()),
+    }
+}
+
+fn record_hash(data: &[u8]) -> String {
+    use base64::Engine;
+    use sha2::Digest;
+    format!(
+        "sha256={}",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(sha2::Sha256::digest(data))
+    )
+}
+
+// RECORD 是 CSV 格式，含有逗号或引号的路径需要加引号
+fn record_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn parse_record_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// 按 RECORD 卸载 root（运行时目录）中的包，没有安装时返回 false
+pub(crate) fn uninstall(root: &std::path::Path, name: &str) -> Result<bool> {
+    let site = root.join("Lib").join("site-packages");
+    let (dist_info, _) = match crate::metadata::find(&site, name)? {
+        Some(found) => found,
+        None => return Ok(false),
+    };
+    let record = dist_info.join("RECORD");
+    if !record.exists() {
+        return Err(format!("{} 不存在，无法卸载 {}", record.display(), name).into());
+    }
+    let mut dirs = std::collections::BTreeSet::new();
+    for line in std::fs::read_to_string(&record)?.lines() {
+        let fields = parse_record_line(line);
+        if fields[0].is_empty() {
+            continue;
+        }
+        let path = fields[0].split('/').fold(site.clone(), |p, i| {
+            if i == ".." {
+                p.parent().unwrap().to_path_buf()
+            } else {
+                p.join(i)
+            }
+        });
+        if !path.starts_with(root) {
+            return Err(format!("RECORD 中含有运行时目录之外的路径：{}", fields[0]).into());
+        }
+        // 编译后 .py 会被替换为同目录下的 .pyc
+        let mut paths = vec![path.clone()];
+        if fields[0].ends_with(".py") {
+            paths.push(std::path::PathBuf::from(format!("{}c", path.display())));
+        }
+        for i in paths {
+            if i.is_file() {
+                std::fs::remove_file(&i)?;
+            }
+        }
+        if let Some(parent) = path.parent() {
+            let pycache = parent.join("__pycache__");
+            if pycache.is_dir() {
+                let stem = path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                for i in std::fs::read_dir(&pycache)? {
+                    let i = i?;
+                    if i.file_name()
+                        .to_string_lossy()
+                        .starts_with(&format!("{}.", stem))
+                    {
+                        std::fs::remove_file(i.path())?;
+                    }
+                }
+                dirs.insert(pycache);
+            }
+            dirs.insert(parent.to_path_buf());
+        }
+    }
+    if dist_info.exists() {
+        std::fs::remove_dir_all(&dist_info)?;
+    }
+    // 从深到浅删除空目录，不删除 site-packages 和运行时目录本身
+    for dir in dirs.into_iter().rev() {
+        let mut dir = dir.as_path();
+        while dir != site && dir != root && dir.starts_with(root) {
+            if !dir.is_dir() || std::fs::read_dir(dir)?.next().is_some() {
+                break;
+            }
+            std::fs::remove_dir(dir)?;
+            dir = dir.parent().unwrap();
+        }
+    }
+    info!("已卸载：{}", name);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 在临时目录中创建一个测试用的运行时目录，每个测试使用不同的目录
+    fn temp_root(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pyembed-wheel-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Lib").join("site-packages")).unwrap();
+        dir
+    }
+
+    // 生成 demo-1.0 的 wheel，bad_hash 为 true 时 RECORD 中 demo/__init__.py 的哈希错误
+    fn build_wheel(dir: &std::path::Path, bad_hash: bool) -> std::path::PathBuf {
+        use std::io::Write;
+        let files: Vec<(&str, &[u8])> = vec![
+            ("demo/__init__.py", b"VALUE = 1\n"),
+            ("demo/util.py", b"def f():\n    pass\n"),
+            ("demo-1.0.data/purelib/demo_extra.py", b"EXTRA = 1\n"),
+            ("demo-1.0.data/scripts/demo-cli", b"#!python\nprint(1)\n"),
+            ("demo-1.0.data/data/share/demo.txt", b"data\n"),
+            (
+                "demo-1.0.dist-info/METADATA",
+                b"Metadata-Version: 2.1\nName: demo\nVersion: 1.0\n",
+            ),
+            (
+                "demo-1.0.dist-info/WHEEL",
+                b"Wheel-Version: 1.0\nRoot-Is-Purelib: true\nTag: py3-none-any\n",
+            ),
+        ];
+        let mut record = String::new();
+        for (name, data) in &files {
+            let hash = if bad_hash && *name == "demo/__init__.py" {
+                record_hash(b"other")
+            } else {
+                record_hash(data)
+            };
+            record += &format!("{},{},{}\n", name, hash, data.len());
+        }
+        record += "demo-1.0.dist-info/RECORD,,\n";
+
+        let path = dir.join("demo-1.0-py3-none-any.whl");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default();
+        for (name, data) in files.iter().copied().chain(std::iter::once((
+            "demo-1.0.dist-info/RECORD",
+            record.as_bytes(),
+        ))) {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn install_scheme_mapping() {
+        let root = temp_root("install");
+        let wheel = build_wheel(&root, false);
+        let metadata = install(&wheel, &root, true).unwrap();
+        assert_eq!(metadata.name, "demo");
+        assert_eq!(metadata.version, "1.0");
+
+        let site = root.join("Lib").join("site-packages");
+        assert!(site.join("demo").join("__init__.py").is_file());
+        assert!(site.join("demo_extra.py").is_file());
+        assert!(root.join("Scripts").join("demo-cli").is_file());
+        assert!(root.join("share").join("demo.txt").is_file());
+        // .data 目录本身不会出现在 site-packages 中
+        assert!(!site.join("demo-1.0.data").exists());
+
+        let dist_info = site.join("demo-1.0.dist-info");
+        assert!(dist_info.join("REQUESTED").is_file());
+        assert!(dist_info.join("INSTALLER").is_file());
+        let record = std::fs::read_to_string(dist_info.join("RECORD")).unwrap();
+        assert!(record.contains("../../Scripts/demo-cli,sha256="));
+        assert!(record.contains("../../share/demo.txt,sha256="));
+        assert!(record.contains("demo_extra.py,sha256="));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn install_rejects_hash_mismatch() {
+        let root = temp_root("hash");
+        let wheel = build_wheel(&root, true);
+        let err = install(&wheel, &root, false).unwrap_err().to_string();
+        assert!(err.contains("demo/__init__.py 的哈希不匹配"), "{}", err);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn uninstall_removes_compiled() {
+        let root = temp_root("uninstall");
+        let wheel = build_wheel(&root, false);
+        install(&wheel, &root, false).unwrap();
+        let site = root.join("Lib").join("site-packages");
+        let package = site.join("demo");
+        // 模拟编译：util.py 替换为同目录的 .pyc，__init__.py 在 __pycache__ 中有缓存
+        std::fs::remove_file(package.join("util.py")).unwrap();
+        std::fs::write(package.join("util.pyc"), b"").unwrap();
+        std::fs::create_dir_all(package.join("__pycache__")).unwrap();
+        std::fs::write(
+            package.join("__pycache__").join("__init__.cpython-311.pyc"),
+            b"",
+        )
+        .unwrap();
+
+        assert!(uninstall(&root, "Demo").unwrap());
+        assert!(!package.exists());
+        assert!(!site.join("demo_extra.py").exists());
+        assert!(!site.join("demo-1.0.dist-info").exists());
+        assert!(!root.join("Scripts").exists());
+        assert!(!root.join("share").exists());
+        assert!(site.is_dir());
+        assert!(!uninstall(&root, "demo").unwrap());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn unsafe_path() {
+        let root = std::path::Path::new("rt");
+        let site = root.join("Lib").join("site-packages");
+        assert!(target_path("../evil.py", "demo-1.0.data", root, &site, "demo").is_err());
+        assert!(target_path(
+            "demo-1.0.data/other/x",
+            "demo-1.0.data",
+            root,
+            &site,
+            "demo"
+        )
+        .is_err());
+        assert_eq!(
+            target_path(
+                "demo-1.0.data/headers/a.h",
+                "demo-1.0.data",
+                root,
+                &site,
+                "demo"
+            )
+            .unwrap(),
+            Some(root.join("Include").join("demo").join("a.h"))
+        );
+    }
+}
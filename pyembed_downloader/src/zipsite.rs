@@ -0,0 +1,210 @@
+// 把纯 Python 包打包为 Lib/site-packages.zip，并加入 ._pth，减少运行时的文件数量
+// 以下包保留在 site-packages 目录中：
+// 包含扩展模块（.pyd、.dll）的包，zipimport 不能加载
+// 带有数据文件并使用了 __file__ 的包，在 zip 中无法按路径读取数据文件
+// 标记为 zip_safe=False（元数据目录中有 not-zip-safe 文件）的包
+// pip、setuptools、wheel 会在清理时按 RECORD 卸载，也不打包
+
+use crate::config::Config;
+use crate::pyproject::normalize_name;
+use crate::Result;
+
+pub(crate) const ZIP_NAME: &str = "site-packages.zip";
+
+// 不打包的包
+const EXCLUDED: [&str; 3] = ["pip", "setuptools", "wheel"];
+
+struct Owner {
+    name: String,
+    top_level: Vec<String>,
+    zip_safe: bool,
+}
+
+// 要在清理之前调用，需要 dist-info 中的 RECORD 确定包含的文件
+pub(crate) fn pack(config: &Config, dir: &std::path::Path, pyver: &str) -> Result<()> {
+    let site = dir.join("Lib").join("site-packages");
+    let owners = owners(&site)?;
+    let allow: Vec<String> = config
+        .zip_packages
+        .iter()
+        .map(|i| normalize_name(i))
+        .collect();
+    for i in allow.iter() {
+        if !owners.iter().any(|o| &o.name == i) {
+            warn!("{} 没有安装，跳过打包", i);
+        }
+    }
+
+    let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(&site)?
+        .map(|i| i.map(|i| i.path()))
+        .collect::<std::io::Result<_>>()?;
+    entries.sort();
+    let mut packed = vec![];
+    for path in entries {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let lower = name.to_ascii_lowercase();
+        if lower.ends_with(".dist-info")
+            || lower.ends_with(".egg-info")
+            || lower.ends_with(".pth")
+            || lower == "__pycache__"
+        {
+            continue;
+        }
+        if path.is_file() && !lower.ends_with(".py") && !lower.ends_with(".pyc") {
+            continue;
+        }
+        let owner = owners.iter().find(|i| i.top_level.contains(&name));
+        if !allow.is_empty() && !owner.is_some_and(|i| allow.contains(&i.name)) {
+            continue;
+        }
+        match unsafe_reason(&path, owner)? {
+            Some(reason) => info!("不打包 {}：{}", name, reason),
+            None => packed.push(path),
+        }
+    }
+    if packed.is_empty() {
+        info!("没有可以打包为 zip 的包");
+        return Ok(());
+    }
+
+    let zippath = dir.join("Lib").join(ZIP_NAME);
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zippath)?);
+    let mut options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    if let Some(epoch) = config.source_date_epoch {
+        options = options.last_modified_time(crate::utility::zip_datetime(epoch));
+    }
+    for path in packed.iter() {
+        info!("打包：{}", path.file_name().unwrap().to_string_lossy());
+        for file in files(path)? {
+            let name = file
+                .strip_prefix(&site)?
+                .to_string_lossy()
+                .replace('\\', "/");
+            // zipimport 只加载与 .py 同目录的 .pyc，不读取 __pycache__，按 Cached、Source 规则保留源文件的包只打包源文件
+            if name.split('/').any(|i| i == "__pycache__") {
+                continue;
+            }
+            zip.start_file(name, options)?;
+            std::io::copy(&mut std::fs::File::open(&file)?, &mut zip)?;
+        }
+    }
+    zip.finish()?;
+    for path in packed.iter() {
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+    }
+    info!("已打包 {} 项到 {}", packed.len(), zippath.display());
+    register(dir, pyver)
+}
+
+// 把 zip 加入 ._pth，路径相对于 ._pth 所在目录
+fn register(dir: &std::path::Path, pyver: &str) -> Result<()> {
+    let pth = dir.join(format!("python{}._pth", crate::utility::python_xy(pyver)));
+    let content = std::fs::read_to_string(&pth)?;
+    let entry = format!("Lib\\{}", ZIP_NAME);
+    let mut lines: Vec<&str> = content.lines().collect();
+    if lines.contains(&entry.as_str()) {
+        return Ok(());
+    }
+    match lines.iter().position(|i| *i == "import site") {
+        Some(n) => lines.insert(n, &entry),
+        None => lines.push(&entry),
+    }
+    std::fs::write(&pth, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+fn owners(site: &std::path::Path) -> Result<Vec<Owner>> {
+    let mut result = vec![];
+    for entry in std::fs::read_dir(site)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let stem = match name
+            .strip_suffix(".dist-info")
+            .or_else(|| name.strip_suffix(".egg-info"))
+        {
+            Some(stem) if path.is_dir() => stem,
+            _ => continue,
+        };
+        let name = match crate::metadata::read(&path) {
+            Ok(metadata) => normalize_name(&metadata.name),
+            Err(_) => normalize_name(stem.split('-').next().unwrap_or_default()),
+        };
+        let mut top_level = crate::modify::top_level(&path)?;
+        if let Ok(content) = std::fs::read_to_string(path.join("top_level.txt")) {
+            for i in content.lines().map(|i| i.trim()).filter(|i| !i.is_empty()) {
+                for i in [i.to_string(), format!("{}.py", i), format!("{}.pyc", i)] {
+                    if !top_level.contains(&i) {
+                        top_level.push(i);
+                    }
+                }
+            }
+        }
+        result.push(Owner {
+            zip_safe: !path.join("not-zip-safe").exists(),
+            name,
+            top_level,
+        });
+    }
+    Ok(result)
+}
+
+fn unsafe_reason(path: &std::path::Path, owner: Option<&Owner>) -> Result<Option<&'static str>> {
+    if let Some(owner) = owner {
+        if EXCLUDED.contains(&owner.name.as_str()) {
+            return Ok(Some("会在清理时卸载"));
+        }
+        if !owner.zip_safe {
+            return Ok(Some("标记为 zip_safe=False"));
+        }
+    }
+    let mut has_code = false;
+    let mut has_data = false;
+    let mut uses_file = false;
+    for file in files(path)? {
+        let name = file
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+        if name.ends_with(".pyd") || name.ends_with(".dll") || name.ends_with(".so") {
+            return Ok(Some("包含扩展模块"));
+        } else if name.ends_with(".py") || name.ends_with(".pyc") {
+            has_code = true;
+            // 编译后的 .pyc 中名称以字符串保存，同样可以搜索
+            if !uses_file {
+                let data = std::fs::read(&file)?;
+                uses_file = data.windows(8).any(|i| i == b"__file__");
+            }
+        } else if name != "py.typed" && !name.ends_with(".pyi") {
+            has_data = true;
+        }
+    }
+    if !has_code {
+        return Ok(Some("不是 Python 包"));
+    }
+    if has_data && uses_file {
+        return Ok(Some("通过 __file__ 访问数据文件"));
+    }
+    Ok(None)
+}
+
+fn files(path: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut result = vec![];
+    if path.is_dir() {
+        let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(path)?
+            .map(|i| i.map(|i| i.path()))
+            .collect::<std::io::Result<_>>()?;
+        entries.sort();
+        for i in entries {
+            result.extend(files(&i)?);
+        }
+    } else if path.is_file() {
+        result.push(path.to_path_buf());
+    }
+    Ok(result)
+}